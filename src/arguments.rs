@@ -1,15 +1,21 @@
 pub mod compatability_mode;
+pub mod quirk_overrides;
 pub mod scale;
 
 use std::path::PathBuf;
 
-use clap::{command, Parser};
+use clap::Parser;
 
 use self::compatability_mode::CompatabilityMode;
+use self::quirk_overrides::QuirkOverrides;
 use self::scale::Scale;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
+#[cfg_attr(
+    not(feature = "audio"),
+    command(after_help = "Built without sound, rebuild with `--features audio` to play it.")
+)]
 pub struct Arguments {
     /// Path for the interpreters executable.
     pub binary_path: PathBuf,
@@ -24,11 +30,40 @@ pub struct Arguments {
     #[arg(short, long, default_value = "white")]
     pub foreground_color: String,
 
-    /// Scale for the emulators UI.
+    /// Colors for XO-CHIP programs, overriding the background and foreground colors.
+    /// Accepts four comma separated colors for pixels that are off, lit on the first
+    /// plane, lit on the second plane and lit on both, such as black,white,#AAA,#555
+    #[arg(short, long)]
+    pub palette: Option<String>,
+
+    /// Scale for the emulators UI, in low resolution pixels. x1 is the same as x2 so high
+    /// resolution pixels are not lost.
     #[arg(short, long, default_value = "x8")]
     pub scale: Option<Scale>,
 
     /// Compatability for SUPER-CHIP programs.
     #[arg(short, long, default_value = "cosmac")]
     pub compatability_mode: CompatabilityMode,
+
+    /// Host keys for the keypad keys 0 to F, in that order. Accepts comma separated
+    /// keys or a single string, such as x123qweasdzc4rfv
+    #[arg(short, long, conflicts_with = "keymap_file")]
+    pub keymap: Option<String>,
+
+    /// File containing a keymap in the same format as `--keymap`.
+    #[arg(long)]
+    pub keymap_file: Option<PathBuf>,
+
+    /// File to keep SUPER-CHIP user flags in between runs, defaults to the users data
+    /// directory.
+    #[arg(long)]
+    pub flags_file: Option<PathBuf>,
+
+    /// Disable the emulators tone.
+    #[cfg(feature = "audio")]
+    #[arg(short, long)]
+    pub mute: bool,
+
+    #[command(flatten)]
+    pub quirks: QuirkOverrides,
 }
@@ -1,8 +1,26 @@
 use clap::ValueEnum;
 
-#[derive(Debug, ValueEnum, Clone, Copy)]
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Eq)]
 pub enum CompatabilityMode {
     Cosmac,
     Super,
     Xo,
 }
+
+impl CompatabilityMode {
+    /// Amount of nested subroutine calls the platform supports.
+    pub const fn stack_depth(self) -> usize {
+        match self {
+            Self::Cosmac => 12,
+            Self::Super | Self::Xo => 16,
+        }
+    }
+
+    /// Amount of addressable memory in bytes.
+    pub const fn memory_size(self) -> usize {
+        match self {
+            Self::Cosmac | Self::Super => 0x1000,
+            Self::Xo => 0x10000,
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use clap::Args;
+
+use crate::emulator::Quirks;
+
+// Individual quirks, each overriding the default of the compatability mode. Kept as a plain
+// comment as clap would otherwise use it as the programs description.
+//
+// These are the only docs for each quirk, `Quirks` refers back to them.
+#[derive(Args, Debug, Default, Clone, Copy)]
+#[command(next_help_heading = "Quirks")]
+pub struct QuirkOverrides {
+    /// Whether 8XY6 and 8XYE shift VY into VX, rather than shifting VX in place.
+    #[arg(long)]
+    pub shift_uses_vy: Option<bool>,
+
+    /// Whether FX55 and FX65 leave I pointing past the last register stored or loaded.
+    #[arg(long)]
+    pub memory_increments_index: Option<bool>,
+
+    /// Whether BNNN jumps to XNN + VX, rather than NNN + V0.
+    #[arg(long)]
+    pub jump_uses_vx: Option<bool>,
+
+    /// Whether 8XY1, 8XY2 and 8XY3 reset VF to 0.
+    #[arg(long)]
+    pub logic_resets_vf: Option<bool>,
+
+    /// Whether DXYN waits for the display to refresh, limiting drawing to one sprite a frame.
+    #[arg(long)]
+    pub display_wait: Option<bool>,
+
+    /// Whether sprites drawn past the right or bottom edge wrap around, rather than being
+    /// clipped.
+    #[arg(long)]
+    pub wrap_sprites: Option<bool>,
+
+    /// Whether scrolling in low resolution moves half as many pixels, rounding up, as
+    /// SUPER-CHIP 1.1 always scrolled by high resolution pixels.
+    #[arg(long)]
+    pub half_pixel_scroll: Option<bool>,
+
+    /// Whether DXYN in high resolution sets VF to the amount of sprite rows that collided or
+    /// were clipped at the bottom edge, rather than just 1 for any collision, like SUPER-CHIP
+    /// 1.1.
+    #[arg(long)]
+    pub count_collided_rows: Option<bool>,
+}
+
+impl QuirkOverrides {
+    /// Replace each quirk in `quirks` that has been overridden.
+    pub const fn apply(self, mut quirks: Quirks) -> Quirks {
+        if let Some(shift_uses_vy) = self.shift_uses_vy {
+            quirks.shift_uses_vy = shift_uses_vy;
+        }
+
+        if let Some(memory_increments_index) = self.memory_increments_index {
+            quirks.memory_increments_index = memory_increments_index;
+        }
+
+        if let Some(jump_uses_vx) = self.jump_uses_vx {
+            quirks.jump_uses_vx = jump_uses_vx;
+        }
+
+        if let Some(logic_resets_vf) = self.logic_resets_vf {
+            quirks.logic_resets_vf = logic_resets_vf;
+        }
+
+        if let Some(display_wait) = self.display_wait {
+            quirks.display_wait = display_wait;
+        }
+
+        if let Some(wrap_sprites) = self.wrap_sprites {
+            quirks.wrap_sprites = wrap_sprites;
+        }
+
+        if let Some(half_pixel_scroll) = self.half_pixel_scroll {
+            quirks.half_pixel_scroll = half_pixel_scroll;
+        }
+
+        if let Some(count_collided_rows) = self.count_collided_rows {
+            quirks.count_collided_rows = count_collided_rows;
+        }
+
+        quirks
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::arguments::compatability_mode::CompatabilityMode;
+
+    #[test]
+    fn test_apply_overrides_each_quirk() {
+        let preset = Quirks::from(CompatabilityMode::Cosmac);
+
+        let overrides = QuirkOverrides {
+            shift_uses_vy:           Some(!preset.shift_uses_vy),
+            memory_increments_index: Some(!preset.memory_increments_index),
+            jump_uses_vx:            Some(!preset.jump_uses_vx),
+            logic_resets_vf:         Some(!preset.logic_resets_vf),
+            display_wait:            Some(!preset.display_wait),
+            wrap_sprites:            Some(!preset.wrap_sprites),
+            half_pixel_scroll:       Some(!preset.half_pixel_scroll),
+            count_collided_rows:     Some(!preset.count_collided_rows),
+        };
+
+        assert_eq!(
+            overrides.apply(preset),
+            Quirks {
+                shift_uses_vy:           !preset.shift_uses_vy,
+                memory_increments_index: !preset.memory_increments_index,
+                jump_uses_vx:            !preset.jump_uses_vx,
+                logic_resets_vf:         !preset.logic_resets_vf,
+                display_wait:            !preset.display_wait,
+                wrap_sprites:            !preset.wrap_sprites,
+                half_pixel_scroll:       !preset.half_pixel_scroll,
+                count_collided_rows:     !preset.count_collided_rows,
+            }
+        );
+    }
+
+    #[test]
+    fn test_apply_keeps_unset_quirks() {
+        let preset = Quirks::from(CompatabilityMode::Super);
+
+        let overrides = QuirkOverrides {
+            wrap_sprites: Some(true),
+            ..QuirkOverrides::default()
+        };
+
+        assert_eq!(QuirkOverrides::default().apply(preset), preset);
+        assert_eq!(
+            overrides.apply(preset),
+            Quirks {
+                wrap_sprites: true,
+                ..preset
+            }
+        );
+    }
+}
@@ -11,6 +11,21 @@ pub enum Scale {
     X32,
 }
 
+impl Scale {
+    /// Half of the scale, which is the same size on screen when applied to a buffer twice as
+    /// large. There is nothing smaller than `X1`, so it stays the same.
+    pub const fn halved(self) -> Self {
+        match self {
+            Self::FitScreen => Self::FitScreen,
+            Self::X1 | Self::X2 => Self::X1,
+            Self::X4 => Self::X2,
+            Self::X8 => Self::X4,
+            Self::X16 => Self::X8,
+            Self::X32 => Self::X16,
+        }
+    }
+}
+
 impl From<Scale> for minifb::Scale {
     fn from(val: Scale) -> Self {
         match val {
@@ -1,6 +1,14 @@
-use anyhow::{bail, Error, Result};
+use anyhow::{bail, Result};
 use hex_color::HexColor;
 
+use crate::emulator::COLOR_COUNT;
+
+/// Colour of pixels only lit on the second XO-CHIP plane when no palette is given.
+pub const SECOND_PLANE_COLOR: HexColor = HexColor::rgb(0xAA, 0xAA, 0xAA);
+
+/// Colour of pixels lit on both XO-CHIP planes when no palette is given.
+pub const BOTH_PLANES_COLOR: HexColor = HexColor::rgb(0x55, 0x55, 0x55);
+
 #[allow(clippy::module_name_repetitions)]
 pub fn parse_color(color: &str) -> Result<HexColor> {
     Ok(match color.to_lowercase().as_str() {
@@ -21,3 +29,17 @@ pub fn parse_color(color: &str) -> Result<HexColor> {
         }
     })
 }
+
+/// Parse four comma separated colours, one for each combination of lit planes.
+pub fn parse_palette(palette: &str) -> Result<[HexColor; COLOR_COUNT]> {
+    let colors = palette
+        .split(',')
+        .map(|color| parse_color(color.trim()))
+        .collect::<Result<Vec<_>>>()?;
+
+    let Ok(colors) = colors.try_into() else {
+        bail!("Palette must have exactly {COLOR_COUNT} colors: `{palette}`");
+    };
+
+    Ok(colors)
+}
@@ -5,8 +5,10 @@ pub trait Draw {
     /// Draw the emulators bitmap grid onto a screen.
     ///
     /// Each item in buffer represents a pixel going from left to right, top to bottom.
-    /// `true` is active, `false` is not.
+    /// Pixels are a mask of the planes they are lit on, so `0` is off, `1` is lit on the
+    /// first plane, `2` on the second and `3` on both, which only XO-CHIP programs use.
     ///
-    /// The screens buffer should be 64 pixels tall and 32 pixels wide.
-    fn draw(&mut self, buffer: &[[bool; 64]; 32]) -> Result<()>;
+    /// The screens buffer is `width` pixels wide and `height` pixels tall, either 64 by 32
+    /// or 128 by 64 in high resolution mode.
+    fn draw(&mut self, buffer: &[u8], width: usize, height: usize) -> Result<()>;
 }
@@ -1,48 +1,94 @@
+mod display;
+mod error;
 mod font;
 mod instruction;
+mod quirks;
+mod stack;
 
-use std::thread::{sleep, sleep_ms};
-use std::time::Duration;
+use std::ops::Range;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use bitvec::order::Msb0;
 use bitvec::view::BitView;
 
-use self::font::FONT;
+use self::display::{Display, ALL_PLANES, PLANE_COUNT};
+pub use self::display::{COLOR_COUNT, HIRES_HEIGHT, HIRES_WIDTH};
+pub use self::error::Error;
+use self::font::{
+    CHARACTER_SIZE, FONT, FONT_ADDRESS, LARGE_CHARACTER_SIZE, LARGE_FONT, LARGE_FONT_ADDRESS,
+};
 use self::instruction::Instruction;
+pub use self::quirks::Quirks;
+use self::stack::Stack;
 use crate::arguments::compatability_mode::CompatabilityMode;
 use crate::draw::Draw;
+use crate::flags::{FlagStorage, FLAG_COUNT};
+use crate::input::Input;
+use crate::sound::{Sound, DEFAULT_PATTERN, DEFAULT_PITCH, PATTERN_SIZE};
 
-const BLANK_DISPLAY: [[bool; 64]; 32] = [[false; 64]; 32];
+/// Amount of frames per second, the timers count down once every frame.
+const FRAME_RATE: u64 = 60;
 
-/// Original spec specified a 48 byte stack,
-/// Since the stack will be represented by u16s
-/// the size can be halved.
-const STACK_SIZE: usize = 24;
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / FRAME_RATE);
 
-#[derive(Debug, Clone, Copy)]
+/// Instructions executed every frame, roughly 700 instructions per second.
+const INSTRUCTIONS_PER_FRAME: usize = 11;
+
+/// Amount of keys on the hexadecimal keypad.
+pub const KEY_COUNT: usize = 16;
+
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Chip8 {
-    memory:             [u8; 4096],
-    display:            [[bool; 64]; 32],
-    stack:              [u16; STACK_SIZE],
-    stack_pointer:      usize,
-    var_registers:      [u8; 16],
-    program_counter:    u16,
-    index_register:     u16,
-    delay_timer:        u8,
-    sound_timer:        u8,
-    compatibility_mode: CompatabilityMode,
+    memory:              Box<[u8]>,
+    display:             Display,
+    /// Mask of the XO-CHIP planes drawn, cleared and scrolled.
+    selected_planes:     u8,
+    stack:               Stack,
+    var_registers:       [u8; 16],
+    /// SUPER-CHIP user flags, kept between runs.
+    flags:               [u8; FLAG_COUNT],
+    flags_changed:       bool,
+    keypad:              [bool; KEY_COUNT],
+    previous_keypad:     [bool; KEY_COUNT],
+    program_counter:     u16,
+    /// Address of the instruction currently executing.
+    instruction_address: u16,
+    /// Opcode of the instruction currently executing.
+    opcode:              u16,
+    index_register:      u16,
+    delay_timer:         u8,
+    sound_timer:         u8,
+    audio_pattern:       [u8; PATTERN_SIZE],
+    pitch:               u8,
+    /// Whether the audio pattern or pitch changed since they were last sent to the audio output.
+    audio_changed:       bool,
+    compatibility_mode:  CompatabilityMode,
+    quirks:              Quirks,
+    /// Set once a sprite is drawn when waiting for the display to refresh.
+    waiting_for_vblank:  bool,
+    /// Set once the program exits with 00FD.
+    exited:              bool,
 }
 
 impl Chip8 {
-    pub fn new(executable: &[u8], compatibility_mode: CompatabilityMode) -> Self {
-        let mut memory = [0; 4096];
+    pub fn new(executable: &[u8], compatibility_mode: CompatabilityMode, quirks: Quirks) -> Self {
+        let mut memory = vec![0; compatibility_mode.memory_size()].into_boxed_slice();
 
         // Insert font into memory
-        for (font_index, memory_index) in (0x050..=0x09F).enumerate() {
+        let font_range = FONT_ADDRESS as usize..FONT_ADDRESS as usize + FONT.len();
+
+        for (font_index, memory_index) in font_range.enumerate() {
             memory[memory_index] = FONT[font_index];
         }
 
+        let large_font_range =
+            LARGE_FONT_ADDRESS as usize..LARGE_FONT_ADDRESS as usize + LARGE_FONT.len();
+
+        memory[large_font_range].copy_from_slice(&LARGE_FONT);
+
         // Load executable
         for (instruction_index, memory_index) in (0x200..(0x200 + executable.len())).enumerate() {
             memory[memory_index] = executable[instruction_index];
@@ -50,64 +96,249 @@ impl Chip8 {
 
         Self {
             memory,
-            display: BLANK_DISPLAY,
+            display: Display::default(),
+            selected_planes: 1,
             program_counter: 0x200,
+            instruction_address: 0x200,
+            opcode: 0,
             index_register: 0,
-            stack: [0; STACK_SIZE],
-            stack_pointer: 0,
+            stack: Stack::new(compatibility_mode.stack_depth()),
             delay_timer: 0,
             sound_timer: 0,
+            audio_pattern: DEFAULT_PATTERN,
+            pitch: DEFAULT_PITCH,
+            audio_changed: false,
             var_registers: [0; 16],
+            flags: [0; FLAG_COUNT],
+            flags_changed: false,
+            keypad: [false; KEY_COUNT],
+            previous_keypad: [false; KEY_COUNT],
             compatibility_mode,
+            quirks,
+            waiting_for_vblank: false,
+            exited: false,
         }
     }
 
-    pub fn run(&mut self, ui: &mut impl Draw) -> Result<()> {
+    /// Run the program until it exits, which is the only way to return `Ok`.
+    ///
+    /// Errors if the program crashes or if any of the front ends fail, such as the window closing.
+    pub fn run(
+        &mut self,
+        ui: &mut (impl Draw + Input),
+        sound: &mut (impl Sound + ?Sized),
+        flags: &mut (impl FlagStorage + ?Sized),
+    ) -> Result<()> {
+        self.flags = flags.load()?;
+
+        let mut next_frame = Instant::now();
+
         loop {
-            let instruction_range =
-                self.program_counter as usize..(self.program_counter + 2) as usize;
+            self.set_keys(ui.keys());
+
+            self.waiting_for_vblank = false;
+
+            for _ in 0..INSTRUCTIONS_PER_FRAME {
+                if let Err(error) = self.step() {
+                    // Keep flags saved earlier in the frame, such as a high score before a crash.
+                    self.save_flags(flags)?;
+
+                    return Err(error);
+                }
+
+                if self.waiting_for_vblank || self.exited {
+                    break;
+                }
+            }
+
+            if self.audio_changed {
+                sound.set_pattern(self.audio_pattern)?;
+                sound.set_pitch(self.pitch)?;
+
+                self.audio_changed = false;
+            }
+
+            sound.set_tone(self.sound_timer > 0)?;
+
+            self.save_flags(flags)?;
+
+            if self.exited {
+                sound.set_tone(false)?;
+
+                return Ok(());
+            }
+
+            self.tick_timers();
 
-            let bytes = &self.memory[instruction_range];
+            ui.draw(
+                self.display.buffer(),
+                self.display.width(),
+                self.display.height(),
+            )?;
 
-            // Concatenate the two bytes together.
-            let instruction = ((u16::from(bytes[0]) << 8) + u16::from(bytes[1])).try_into()?;
+            next_frame += FRAME_DURATION;
 
-            self.program_counter += 2;
+            let now = Instant::now();
 
-            self.run_instruction(instruction, ui)?;
+            if next_frame > now {
+                sleep(next_frame - now);
+            } else {
+                // Running behind, rather than rushing to catch up start counting from now.
+                next_frame = now;
+            }
+        }
+    }
 
-            ui.draw(&self.display)?;
+    /// Save the flags if they changed since they were last saved.
+    fn save_flags(&mut self, flags: &mut (impl FlagStorage + ?Sized)) -> Result<()> {
+        if self.flags_changed {
+            flags.save(self.flags)?;
 
-            // Run 700 instructions per second.
-            sleep(Duration::from_micros(1428));
+            self.flags_changed = false;
         }
+
+        Ok(())
     }
 
-    #[allow(clippy::too_many_lines)]
-    fn run_instruction(&mut self, instruction: Instruction, ui: &mut impl Draw) -> Result<()> {
-        println!("{instruction:?}");
+    /// Fetch, decode and execute a single instruction.
+    fn step(&mut self) -> Result<()> {
+        let program_counter = self.program_counter;
 
+        let Some(opcode) = self.opcode_at(program_counter) else {
+            return Err(Error::ProgramCounterOutOfBounds { program_counter }.into());
+        };
+
+        self.opcode = opcode;
+        self.instruction_address = program_counter;
+
+        let instruction = Instruction::try_from(opcode).map_err(|_| Error::UnknownOpcode {
+            opcode,
+            program_counter,
+        })?;
+
+        if !instruction.is_supported(self.compatibility_mode) {
+            return Err(Error::UnsupportedOpcode {
+                opcode,
+                program_counter,
+                compatibility_mode: self.compatibility_mode,
+            }
+            .into());
+        }
+
+        self.program_counter = program_counter.wrapping_add(2);
+
+        self.run_instruction(instruction)
+    }
+
+    /// Opcode stored at `address`, if it lies within addressable memory.
+    fn opcode_at(&self, address: u16) -> Option<u16> {
+        let address = address as usize;
+
+        let bytes = self.memory.get(address..address + 2)?;
+
+        // Concatenate the two bytes together.
+        Some((u16::from(bytes[0]) << 8) + u16::from(bytes[1]))
+    }
+
+    /// Skip over the next instruction, which is twice as long if it is F000 NNNN.
+    fn skip_instruction(&mut self) {
+        let length = if self.opcode_at(self.program_counter) == Some(0xF000)
+            && Instruction::SetLongIndexRegister.is_supported(self.compatibility_mode)
+        {
+            4
+        } else {
+            2
+        };
+
+        self.program_counter = self.program_counter.wrapping_add(length);
+    }
+
+    /// Range of `length` bytes of memory from `address`,
+    /// errors if any of it falls outside of memory.
+    fn memory_range(&self, address: usize, length: usize) -> Result<Range<usize>, Error> {
+        if address + length > self.memory.len() {
+            return Err(Error::MemoryOutOfBounds {
+                opcode: self.opcode,
+                program_counter: self.instruction_address,
+                address,
+            });
+        }
+
+        Ok(address..address + length)
+    }
+
+    /// Count both timers down by one, should be called at 60Hz.
+    const fn tick_timers(&mut self) {
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+    }
+
+    /// Return addresses of the subroutines currently being executed,
+    /// from the oldest to the most recent call.
+    pub fn call_stack(&self) -> &[u16] { self.stack.addresses() }
+
+    /// Update the state of the keypad.
+    ///
+    /// Each item represents whether the key of that index is held down.
+    const fn set_keys(&mut self, keys: [bool; KEY_COUNT]) {
+        self.previous_keypad = self.keypad;
+        self.keypad = keys;
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn run_instruction(&mut self, instruction: Instruction) -> Result<()> {
         match instruction {
-            Instruction::ClearScreen => self.display = BLANK_DISPLAY,
+            Instruction::ScrollDown(amount) => {
+                self.display
+                    .scroll_down(self.scroll_amount(amount as usize), self.selected_planes);
+            }
+            Instruction::ScrollUp(amount) => {
+                self.display
+                    .scroll_up(self.scroll_amount(amount as usize), self.selected_planes);
+            }
+            Instruction::ClearScreen => self.display.clear(self.selected_planes),
             Instruction::Return => {
-                self.program_counter = self.stack[self.stack_pointer];
-                self.stack[self.stack_pointer] = 0;
-                self.stack_pointer -= 1;
+                let Some(address) = self.stack.pop() else {
+                    return Err(Error::StackUnderflow {
+                        opcode:          self.opcode,
+                        program_counter: self.instruction_address,
+                    }
+                    .into());
+                };
+
+                self.program_counter = address;
+            }
+            Instruction::ScrollRight => {
+                self.display
+                    .scroll_right(self.scroll_amount(4), self.selected_planes);
             }
+            Instruction::ScrollLeft => {
+                self.display
+                    .scroll_left(self.scroll_amount(4), self.selected_planes);
+            }
+            Instruction::Exit => self.exited = true,
+            Instruction::LowResolution => self.display.set_hires(false),
+            Instruction::HighResolution => self.display.set_hires(true),
             Instruction::Goto(address) => self.program_counter = address,
             Instruction::Subroutine(address) => {
-                self.stack_pointer += 1;
-                self.stack[self.stack_pointer] = self.program_counter;
+                if self.stack.push(self.program_counter).is_none() {
+                    return Err(Error::StackOverflow {
+                        opcode:          self.opcode,
+                        program_counter: self.instruction_address,
+                    }
+                    .into());
+                }
+
                 self.program_counter = address;
             }
             Instruction::IsEqualVal { register, value } => {
                 if self.var_registers[register as usize] == value {
-                    self.program_counter += 2;
+                    self.skip_instruction();
                 }
             }
             Instruction::NotEqualVal { register, value } => {
                 if self.var_registers[register as usize] != value {
-                    self.program_counter += 2;
+                    self.skip_instruction();
                 }
             }
             Instruction::IsEqual {
@@ -117,7 +348,31 @@ impl Chip8 {
                 if self.var_registers[register_x as usize]
                     == self.var_registers[register_y as usize]
                 {
-                    self.program_counter += 2;
+                    self.skip_instruction();
+                }
+            }
+            Instruction::StoreRange {
+                register_x,
+                register_y,
+            } => {
+                let count = register_x.abs_diff(register_y) as usize + 1;
+                let range = self.memory_range(self.index_register as usize, count)?;
+
+                for (offset, address) in range.enumerate() {
+                    self.memory[address] =
+                        self.var_registers[range_register(register_x, register_y, offset)];
+                }
+            }
+            Instruction::LoadRange {
+                register_x,
+                register_y,
+            } => {
+                let count = register_x.abs_diff(register_y) as usize + 1;
+                let range = self.memory_range(self.index_register as usize, count)?;
+
+                for (offset, address) in range.enumerate() {
+                    self.var_registers[range_register(register_x, register_y, offset)] =
+                        self.memory[address];
                 }
             }
             Instruction::SetVal { register, value } => {
@@ -138,31 +393,37 @@ impl Chip8 {
                 register_y,
             } => {
                 self.var_registers[register_x as usize] |= self.var_registers[register_y as usize];
+
+                self.reset_flag_after_logic();
             }
             Instruction::And {
                 register_x,
                 register_y,
             } => {
                 self.var_registers[register_x as usize] &= self.var_registers[register_y as usize];
+
+                self.reset_flag_after_logic();
             }
             Instruction::Xor {
                 register_x,
                 register_y,
             } => {
                 self.var_registers[register_x as usize] ^= self.var_registers[register_y as usize];
+
+                self.reset_flag_after_logic();
             }
+            // The result is written before VF so that VF holds the flag when it is also VX.
             Instruction::Add {
                 register_x,
                 register_y,
             } => {
-                let y = u16::from(self.var_registers[register_y as usize]);
-                let register_x = &mut u16::from(self.var_registers[register_x as usize]);
+                let x = self.var_registers[register_x as usize];
+                let y = self.var_registers[register_y as usize];
 
-                if (*register_x + y) > 255 {
-                    self.var_registers[0xF] = 1;
-                }
+                let (result, carry) = x.overflowing_add(y);
 
-                *register_x = register_x.wrapping_add(y);
+                self.var_registers[register_x as usize] = result;
+                self.var_registers[0xF] = u8::from(carry);
             }
             Instruction::SubtractRight {
                 register_x,
@@ -171,23 +432,17 @@ impl Chip8 {
                 let x = self.var_registers[register_x as usize];
                 let y = self.var_registers[register_y as usize];
 
-                self.var_registers[0xF] = u8::from(x > y);
                 self.var_registers[register_x as usize] = x.wrapping_sub(y);
+                self.var_registers[0xF] = u8::from(x >= y);
             }
-            Instruction::ShiftLeft {
+            Instruction::ShiftRight {
                 register_x,
                 register_y,
             } => {
-                let value = match self.compatibility_mode {
-                    CompatabilityMode::Cosmac => self.var_registers[register_y as usize],
-                    _ => self.var_registers[register_x as usize],
-                };
+                let value = self.shift_source(register_x, register_y);
 
-                let shifted_value = value << 1;
-                let shifted_bit = value & 1;
-
-                self.var_registers[register_x as usize] = shifted_value;
-                self.var_registers[0xF] = shifted_bit;
+                self.var_registers[register_x as usize] = value >> 1;
+                self.var_registers[0xF] = value & 1;
             }
             Instruction::SubtractLeft {
                 register_x,
@@ -196,23 +451,17 @@ impl Chip8 {
                 let x = self.var_registers[register_x as usize];
                 let y = self.var_registers[register_y as usize];
 
-                self.var_registers[0xF] = u8::from(y > x);
                 self.var_registers[register_x as usize] = y.wrapping_sub(x);
+                self.var_registers[0xF] = u8::from(y >= x);
             }
-            Instruction::ShiftRight {
+            Instruction::ShiftLeft {
                 register_x,
                 register_y,
             } => {
-                let value = match self.compatibility_mode {
-                    CompatabilityMode::Cosmac => self.var_registers[register_y as usize],
-                    _ => self.var_registers[register_x as usize],
-                };
+                let value = self.shift_source(register_x, register_y);
 
-                let shifted_value = value >> 1;
-                let shifted_bit = value & 0b1000_0000;
-
-                self.var_registers[register_x as usize] = shifted_value;
-                self.var_registers[0xF] = shifted_bit;
+                self.var_registers[register_x as usize] = value << 1;
+                self.var_registers[0xF] = value >> 7;
             }
             Instruction::NotEqual {
                 register_x,
@@ -221,10 +470,20 @@ impl Chip8 {
                 if self.var_registers[register_x as usize]
                     != self.var_registers[register_y as usize]
                 {
-                    self.program_counter += 2;
+                    self.skip_instruction();
                 }
             }
             Instruction::SetIndexRegister(value) => self.index_register = value,
+            Instruction::GotoOffset(address) => {
+                let offset_register = if self.quirks.jump_uses_vx {
+                    (address >> 8) & 0xF
+                } else {
+                    0
+                };
+
+                self.program_counter =
+                    address + u16::from(self.var_registers[offset_register as usize]);
+            }
             Instruction::Rand { register, value } => {
                 let random_value = rand::random::<u8>() & value;
                 self.var_registers[register as usize] = random_value;
@@ -234,51 +493,1398 @@ impl Chip8 {
                 y_coord_register,
                 sprite_height,
             } => {
-                let column = self.var_registers[x_coord_register as usize] % 64;
-                let row = self.var_registers[y_coord_register as usize] % 32;
+                let column =
+                    self.var_registers[x_coord_register as usize] as usize % self.display.width();
+                let row =
+                    self.var_registers[y_coord_register as usize] as usize % self.display.height();
+
+                // SUPER-CHIP draws a 16x16 sprite when the height is 0.
+                if sprite_height == 0
+                    && !matches!(self.compatibility_mode, CompatabilityMode::Cosmac)
+                {
+                    self.draw(column, row, 16, 2)?;
+                } else {
+                    self.draw(column, row, sprite_height as usize, 1)?;
+                }
+
+                // The COSMAC waited for the display to refresh before drawing,
+                // so no more instructions run until the next frame.
+                self.waiting_for_vblank = self.quirks.display_wait;
+            }
+            Instruction::GetDelayTimer { register } => {
+                self.var_registers[register as usize] = self.delay_timer;
+            }
+            Instruction::IsKeyPressed { register } => {
+                if self.keypad[(self.var_registers[register as usize] & 0xF) as usize] {
+                    self.skip_instruction();
+                }
+            }
+            Instruction::NotKeyPressed { register } => {
+                if !self.keypad[(self.var_registers[register as usize] & 0xF) as usize] {
+                    self.skip_instruction();
+                }
+            }
+            Instruction::GetKey { register } => {
+                // Like the original COSMAC, wait for a key to be released rather than pressed.
+                let released_key = (0..=0xF)
+                    .find(|&key| self.previous_keypad[key as usize] && !self.keypad[key as usize]);
+
+                if let Some(key) = released_key {
+                    self.var_registers[register as usize] = key;
+
+                    // Each release only satisfies a single wait.
+                    self.previous_keypad[key as usize] = false;
+                } else {
+                    // Keep executing this instruction until a key is released.
+                    self.program_counter = self.program_counter.wrapping_sub(2);
+                }
+            }
+            Instruction::SetDelayTimer { register } => {
+                self.delay_timer = self.var_registers[register as usize];
+            }
+            Instruction::SetSoundTimer { register } => {
+                self.sound_timer = self.var_registers[register as usize];
+            }
+            Instruction::AddToIndex { register } => {
+                self.index_register = self
+                    .index_register
+                    .wrapping_add(u16::from(self.var_registers[register as usize]));
+            }
+            Instruction::FontCharacter { register } => {
+                let character = u16::from(self.var_registers[register as usize] & 0xF);
+
+                self.index_register = FONT_ADDRESS + character * CHARACTER_SIZE;
+            }
+            Instruction::LargeFontCharacter { register } => {
+                let character = u16::from(self.var_registers[register as usize] & 0xF);
+
+                self.index_register = LARGE_FONT_ADDRESS + character * LARGE_CHARACTER_SIZE;
+            }
+            Instruction::DecimalConversion { register } => {
+                let value = self.var_registers[register as usize];
+                let range = self.memory_range(self.index_register as usize, 3)?;
+
+                self.memory[range].copy_from_slice(&[value / 100, value / 10 % 10, value % 10]);
+            }
+            Instruction::SaveFlags { register } => {
+                let count = register as usize + 1;
+
+                self.flags[..count].copy_from_slice(&self.var_registers[..count]);
+                self.flags_changed = true;
+            }
+            Instruction::LoadFlags { register } => {
+                let count = register as usize + 1;
+
+                self.var_registers[..count].copy_from_slice(&self.flags[..count]);
+            }
+            Instruction::SetLongIndexRegister => {
+                let range = self.memory_range(self.program_counter as usize, 2)?;
+
+                self.index_register =
+                    u16::from_be_bytes([self.memory[range.start], self.memory[range.start + 1]]);
+                self.program_counter = self.program_counter.wrapping_add(2);
+            }
+            Instruction::SelectPlanes(planes) => self.selected_planes = planes & ALL_PLANES,
+            Instruction::LoadAudioPattern => {
+                let range = self.memory_range(self.index_register as usize, PATTERN_SIZE)?;
+
+                self.audio_pattern.copy_from_slice(&self.memory[range]);
+                self.audio_changed = true;
+            }
+            Instruction::SetPitch { register } => {
+                self.pitch = self.var_registers[register as usize];
+                self.audio_changed = true;
+            }
+            Instruction::StoreMemory { register } => {
+                let count = register as usize + 1;
+                let range = self.memory_range(self.index_register as usize, count)?;
 
-                self.draw(column, row, sprite_height, ui)?;
+                self.memory[range].copy_from_slice(&self.var_registers[..count]);
+
+                self.increment_index_after_memory(register);
+            }
+            Instruction::LoadMemory { register } => {
+                let count = register as usize + 1;
+                let range = self.memory_range(self.index_register as usize, count)?;
+
+                self.var_registers[..count].copy_from_slice(&self.memory[range]);
+
+                self.increment_index_after_memory(register);
             }
         }
 
         Ok(())
     }
 
+    const fn reset_flag_after_logic(&mut self) {
+        if self.quirks.logic_resets_vf {
+            self.var_registers[0xF] = 0;
+        }
+    }
+
+    /// Value shifted by 8XY6 and 8XYE.
+    const fn shift_source(&self, register_x: u8, register_y: u8) -> u8 {
+        if self.quirks.shift_uses_vy {
+            self.var_registers[register_y as usize]
+        } else {
+            self.var_registers[register_x as usize]
+        }
+    }
+
+    fn increment_index_after_memory(&mut self, register: u8) {
+        if self.quirks.memory_increments_index {
+            self.index_register = self.index_register.wrapping_add(u16::from(register) + 1);
+        }
+    }
+
+    /// Pixels to scroll by for a scroll of `amount` high resolution pixels.
+    ///
+    /// Halved amounts round up, as half a low resolution pixel can't be shown and
+    /// rounding down would make a scroll of 1 do nothing.
+    const fn scroll_amount(&self, amount: usize) -> usize {
+        if self.quirks.half_pixel_scroll && !self.display.is_hires() {
+            amount.div_ceil(2)
+        } else {
+            amount
+        }
+    }
+
+    /// XOR the sprite at the index register onto the display, setting VF if any lit
+    /// pixel is turned off.
+    ///
+    /// Each row of the sprite is `row_width` bytes wide. Every selected plane gets its own
+    /// sprite, stored one after the other from the first plane.
+    ///
+    /// Parts of the sprite past the right or bottom edge wrap around to the opposite edge
+    /// or are clipped, depending on the quirks.
     fn draw(
         &mut self,
-        start_column: u8,
-        start_row: u8,
-        sprite_height: u8,
-        ui: &mut impl Draw,
+        start_column: usize,
+        start_row: usize,
+        sprite_height: usize,
+        row_width: usize,
     ) -> Result<()> {
-        self.var_registers[0xF] = 0;
+        let sprite_length = sprite_height * row_width;
 
-        let sprite_address = self.index_register as usize;
+        let width = self.display.width();
+        let height = self.display.height();
 
-        'a: for row in 0..sprite_height as usize {
-            let byte = self.memory[sprite_address + row];
+        // SUPER-CHIP 1.1 reported the amount of rows rather than just whether any collided.
+        let count_rows = self.quirks.count_collided_rows && self.display.is_hires();
 
-            for (column, bit) in byte.view_bits::<Msb0>().iter().enumerate() {
-                let Some(display_row) = self.display.get_mut(start_row as usize + row) else {
-                    // Rest of sprite goes out of bounds, end drawing.
-                    break 'a;
-                };
+        let mut sprite_address = self.index_register as usize;
+        let mut collided_rows = 0;
+
+        for plane in (0..PLANE_COUNT).map(|plane| 1 << plane) {
+            if self.selected_planes & plane == 0 {
+                continue;
+            }
+
+            let sprite_range = self.memory_range(sprite_address, sprite_length)?;
+
+            sprite_address += sprite_length;
+
+            for (row, bytes) in self.memory[sprite_range].chunks(row_width).enumerate() {
+                let mut display_row = start_row + row;
+
+                if self.quirks.wrap_sprites {
+                    display_row %= height;
+                } else if display_row >= height {
+                    // Rest of sprite goes out of bounds, which SUPER-CHIP counts as collisions.
+                    if count_rows {
+                        collided_rows += sprite_height - row;
+                    }
 
-                let Some(display_bit) = display_row.get_mut(start_column as usize + column) else {
-                    // Current row goe out of bounds, go to next row.
                     break;
-                };
+                }
+
+                let mut collided = false;
+
+                for (column, bit) in bytes.view_bits::<Msb0>().iter().enumerate() {
+                    let mut display_column = start_column + column;
+
+                    if self.quirks.wrap_sprites {
+                        display_column %= width;
+                    } else if display_column >= width {
+                        // Current row goes out of bounds, go to next row.
+                        break;
+                    }
 
-                if *display_bit {
-                    self.var_registers[0xF] = 1;
+                    // Turning off a lit pixel is a collision.
+                    if *bit && self.display.toggle(display_column, display_row, plane) {
+                        collided = true;
+                    }
                 }
 
-                *display_bit = *bit;
+                if collided {
+                    collided_rows += 1;
+                }
             }
         }
 
-        ui.draw(&self.display)?;
+        self.var_registers[0xF] = if count_rows {
+            u8::try_from(collided_rows).unwrap_or(u8::MAX)
+        } else {
+            u8::from(collided_rows > 0)
+        };
 
         Ok(())
     }
 }
+
+/// Register `offset` steps from `register_x` towards `register_y`,
+/// counting down when the range is reversed.
+const fn range_register(register_x: u8, register_y: u8, offset: usize) -> usize {
+    if register_x <= register_y {
+        register_x as usize + offset
+    } else {
+        register_x as usize - offset
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+    use crate::sound::waveform::Waveform;
+    use crate::sound::Silent;
+
+    /// Front end that plays back a fixed set of keys and stops after a number of draws.
+    struct ScriptedUi {
+        keys:  [bool; KEY_COUNT],
+        draws: usize,
+    }
+
+    impl Draw for ScriptedUi {
+        fn draw(&mut self, _buffer: &[u8], _width: usize, _height: usize) -> Result<()> {
+            if self.draws == 0 {
+                anyhow::bail!("Script finished");
+            }
+
+            self.draws -= 1;
+
+            Ok(())
+        }
+    }
+
+    impl Input for ScriptedUi {
+        fn keys(&mut self) -> [bool; KEY_COUNT] { self.keys }
+    }
+
+    /// Audio output that keeps track of whether the tone was active every frame,
+    /// and the first 16 samples played each active frame at 4000 samples per second.
+    #[derive(Default)]
+    struct RecordingSound {
+        frames:   Vec<bool>,
+        samples:  Vec<bool>,
+        waveform: Waveform,
+    }
+
+    impl Sound for RecordingSound {
+        fn set_tone(&mut self, active: bool) -> Result<()> {
+            self.frames.push(active);
+
+            if active {
+                for _ in 0..16 {
+                    self.samples.push(self.waveform.next_sample(4000.0));
+                }
+            }
+
+            Ok(())
+        }
+
+        fn set_pattern(&mut self, pattern: [u8; PATTERN_SIZE]) -> Result<()> {
+            self.waveform.set_pattern(pattern);
+
+            Ok(())
+        }
+
+        fn set_pitch(&mut self, pitch: u8) -> Result<()> {
+            self.waveform.set_pitch(pitch);
+
+            Ok(())
+        }
+    }
+
+    /// Flag storage that keeps the flags in memory and counts how often they were saved.
+    #[derive(Default)]
+    struct RecordingFlags {
+        flags: [u8; FLAG_COUNT],
+        saves: usize,
+    }
+
+    impl FlagStorage for RecordingFlags {
+        fn load(&mut self) -> Result<[u8; FLAG_COUNT]> { Ok(self.flags) }
+
+        fn save(&mut self, flags: [u8; FLAG_COUNT]) -> Result<()> {
+            self.flags = flags;
+            self.saves += 1;
+
+            Ok(())
+        }
+    }
+
+    /// Create an emulator with the default quirks of `compatibility_mode`.
+    fn chip8(program: &[u8], compatibility_mode: CompatabilityMode) -> Chip8 {
+        Chip8::new(program, compatibility_mode, compatibility_mode.into())
+    }
+
+    fn run(chip: &mut Chip8, instruction: Instruction) {
+        chip.run_instruction(instruction).unwrap();
+    }
+
+    #[test]
+    fn test_skip_if_key_pressed() {
+        let mut chip = chip8(&[], CompatabilityMode::Cosmac);
+        chip.var_registers[0x1] = 0xA;
+
+        let mut keys = [false; KEY_COUNT];
+        keys[0xA] = true;
+        chip.set_keys(keys);
+
+        run(&mut chip, Instruction::IsKeyPressed { register: 0x1 });
+        assert_eq!(chip.program_counter, 0x202);
+
+        run(&mut chip, Instruction::NotKeyPressed { register: 0x1 });
+        assert_eq!(chip.program_counter, 0x202);
+    }
+
+    #[test]
+    fn test_run_reads_keys_from_front_end() {
+        // Skip the first infinite loop if key 5 is pressed.
+        let program = [0x61, 0x05, 0xE1, 0x9E, 0x12, 0x04, 0x12, 0x06];
+        let mut chip = chip8(&program, CompatabilityMode::Cosmac);
+
+        let mut keys = [false; KEY_COUNT];
+        keys[0x5] = true;
+
+        let mut ui = ScriptedUi { keys, draws: 8 };
+
+        assert!(chip
+            .run(&mut ui, &mut Silent, &mut RecordingFlags::default())
+            .is_err());
+        assert_eq!(chip.program_counter, 0x206);
+    }
+
+    #[test]
+    fn test_timers_count_down_once_per_frame() {
+        // Set the delay timer to 60 then loop forever.
+        let program = [0x6A, 0x3C, 0xFA, 0x15, 0x12, 0x04];
+        let mut chip = chip8(&program, CompatabilityMode::Cosmac);
+
+        let mut ui = ScriptedUi {
+            keys:  [false; KEY_COUNT],
+            draws: 9,
+        };
+
+        assert!(chip
+            .run(&mut ui, &mut Silent, &mut RecordingFlags::default())
+            .is_err());
+        assert_eq!(chip.delay_timer, 50);
+    }
+
+    #[test]
+    fn test_display_wait() {
+        // Draw twice then loop forever.
+        let program = [0xD0, 0x01, 0xD0, 0x01, 0x12, 0x04];
+
+        let mut ui = ScriptedUi {
+            keys:  [false; KEY_COUNT],
+            draws: 0,
+        };
+
+        let mut chip = chip8(&program, CompatabilityMode::Cosmac);
+
+        assert!(chip
+            .run(&mut ui, &mut Silent, &mut RecordingFlags::default())
+            .is_err());
+        assert_eq!(chip.program_counter, 0x202);
+
+        let mut chip = chip8(&program, CompatabilityMode::Super);
+
+        assert!(chip
+            .run(&mut ui, &mut Silent, &mut RecordingFlags::default())
+            .is_err());
+        assert_eq!(chip.program_counter, 0x204);
+    }
+
+    #[test]
+    fn test_tone_active_while_sound_timer_is_set() {
+        // Set the sound timer to 3 then loop forever.
+        let program = [0x6A, 0x03, 0xFA, 0x18, 0x12, 0x04];
+        let mut chip = chip8(&program, CompatabilityMode::Cosmac);
+
+        let mut ui = ScriptedUi {
+            keys:  [false; KEY_COUNT],
+            draws: 5,
+        };
+        let mut sound = RecordingSound::default();
+
+        assert!(chip
+            .run(&mut ui, &mut sound, &mut RecordingFlags::default())
+            .is_err());
+        assert_eq!(sound.frames, [true, true, true, false, false, false]);
+    }
+
+    #[test]
+    fn test_plays_loaded_audio_pattern() {
+        // Load the pattern after the program, double the pitch then beep for a frame.
+        let mut program = vec![
+            0xA2, 0x0E, 0xF0, 0x02, 0x6A, 0x70, 0xFA, 0x3A, 0x6B, 0x01, 0xFB, 0x18, 0x12, 0x0C,
+        ];
+        program.extend([0b1100_1100, 0b1111_0000]);
+        program.extend([0; 14]);
+
+        let mut chip = chip8(&program, CompatabilityMode::Xo);
+
+        let mut ui = ScriptedUi {
+            keys:  [false; KEY_COUNT],
+            draws: 1,
+        };
+        let mut sound = RecordingSound::default();
+
+        assert!(chip
+            .run(&mut ui, &mut sound, &mut RecordingFlags::default())
+            .is_err());
+        assert_eq!(sound.frames, [true, false]);
+        // Every other bit is played at double the pitch.
+        assert_eq!(
+            sound.samples[..8],
+            [true, false, true, false, true, true, false, false]
+        );
+        assert_eq!(sound.samples[8..], [false; 8]);
+    }
+
+    #[test]
+    fn test_goto_offset() {
+        let mut chip = chip8(&[], CompatabilityMode::Cosmac);
+        chip.var_registers[0x0] = 0x10;
+        chip.var_registers[0x3] = 0x20;
+
+        run(&mut chip, Instruction::GotoOffset(0x345));
+        assert_eq!(chip.program_counter, 0x355);
+
+        chip.quirks.jump_uses_vx = true;
+
+        run(&mut chip, Instruction::GotoOffset(0x345));
+        assert_eq!(chip.program_counter, 0x365);
+    }
+
+    #[test]
+    fn test_unknown_opcode_reports_address() {
+        let program = [0x60, 0x01, 0xF0, 0xFF];
+        let mut chip = chip8(&program, CompatabilityMode::Cosmac);
+
+        let error = chip.step().and_then(|()| chip.step());
+
+        assert_eq!(
+            error.unwrap_err().downcast::<Error>().unwrap(),
+            Error::UnknownOpcode {
+                opcode:          0xF0FF,
+                program_counter: 0x202,
+            }
+        );
+    }
+
+    #[test]
+    fn test_unsupported_opcode() {
+        let program = [0xF0, 0x02];
+        let mut chip = chip8(&program, CompatabilityMode::Super);
+
+        let error = chip.step().unwrap_err().downcast::<Error>();
+
+        assert_eq!(
+            error.unwrap(),
+            Error::UnsupportedOpcode {
+                opcode:             0xF002,
+                program_counter:    0x200,
+                compatibility_mode: CompatabilityMode::Super,
+            }
+        );
+    }
+
+    #[test]
+    fn test_memory_out_of_bounds() {
+        let program = [0xAF, 0xFF, 0xF3, 0x55];
+        let mut chip = chip8(&program, CompatabilityMode::Cosmac);
+
+        chip.step().unwrap();
+        let error = chip.step().unwrap_err().downcast::<Error>();
+
+        assert_eq!(
+            error.unwrap(),
+            Error::MemoryOutOfBounds {
+                opcode:          0xF355,
+                program_counter: 0x202,
+                address:         0xFFF,
+            }
+        );
+    }
+
+    #[test]
+    fn test_long_index_register() {
+        // Load I with 0xF000, store V0 there, then skip over another long load.
+        let program = [
+            0xF0, 0x00, 0xF0, 0x00, 0x60, 0x2A, 0xF0, 0x55, 0x30, 0x2A, 0xF0, 0x00, 0x12, 0x34,
+        ];
+        let mut chip = chip8(&program, CompatabilityMode::Xo);
+
+        for _ in 0..4 {
+            chip.step().unwrap();
+        }
+
+        assert_eq!(chip.memory[0xF000], 0x2A);
+        assert_eq!(chip.index_register, 0xF000);
+        assert_eq!(chip.program_counter, 0x20E);
+    }
+
+    #[test]
+    fn test_memory_size() {
+        let program = [0xF0, 0x00, 0x10, 0x00, 0xF0, 0x65];
+
+        let mut chip = chip8(&program, CompatabilityMode::Xo);
+
+        chip.step().unwrap();
+        chip.step().unwrap();
+
+        assert_eq!(chip.var_registers[0], 0);
+
+        let program = [0xAF, 0xFF, 0xF1, 0x65];
+        let mut chip = chip8(&program, CompatabilityMode::Super);
+
+        chip.step().unwrap();
+
+        assert!(chip.step().is_err());
+    }
+
+    #[test]
+    fn test_register_ranges() {
+        let mut chip = chip8(&[], CompatabilityMode::Xo);
+
+        chip.var_registers[..4].copy_from_slice(&[1, 2, 3, 4]);
+        chip.index_register = 0x300;
+
+        run(
+            &mut chip,
+            Instruction::StoreRange {
+                register_x: 1,
+                register_y: 3,
+            },
+        );
+        run(
+            &mut chip,
+            Instruction::StoreRange {
+                register_x: 2,
+                register_y: 0,
+            },
+        );
+
+        assert_eq!(chip.memory[0x300..0x303], [3, 2, 1]);
+        assert_eq!(chip.index_register, 0x300);
+
+        run(
+            &mut chip,
+            Instruction::LoadRange {
+                register_x: 6,
+                register_y: 4,
+            },
+        );
+
+        assert_eq!(chip.var_registers[4..7], [1, 2, 3]);
+        assert_eq!(chip.index_register, 0x300);
+    }
+
+    #[test]
+    fn test_register_ranges_need_xo_chip() {
+        let program = [0x50, 0x12];
+        let mut chip = chip8(&program, CompatabilityMode::Super);
+
+        let error = chip.step().unwrap_err().downcast::<Error>();
+
+        assert!(matches!(error.unwrap(), Error::UnsupportedOpcode { .. }));
+    }
+
+    #[test]
+    fn test_stack_depth() {
+        // Recurse forever.
+        let program = [0x22, 0x00];
+        let mut chip = chip8(&program, CompatabilityMode::Cosmac);
+
+        for _ in 0..12 {
+            chip.step().unwrap();
+        }
+
+        assert_eq!(chip.call_stack(), [0x202; 12]);
+
+        let error = chip.step().unwrap_err().downcast::<Error>();
+
+        assert_eq!(
+            error.unwrap(),
+            Error::StackOverflow {
+                opcode:          0x2200,
+                program_counter: 0x200,
+            }
+        );
+    }
+
+    #[test]
+    fn test_stack_underflow() {
+        let program = [0x00, 0xEE];
+        let mut chip = chip8(&program, CompatabilityMode::Super);
+
+        let error = chip.step().unwrap_err().downcast::<Error>();
+
+        assert_eq!(
+            error.unwrap(),
+            Error::StackUnderflow {
+                opcode:          0x00EE,
+                program_counter: 0x200,
+            }
+        );
+    }
+
+    #[test]
+    fn test_subroutine_returns() {
+        let mut chip = chip8(&[], CompatabilityMode::Super);
+
+        run(&mut chip, Instruction::Subroutine(0x400));
+        assert_eq!(chip.call_stack(), [0x200]);
+
+        run(&mut chip, Instruction::Return);
+        assert_eq!(chip.program_counter, 0x200);
+        assert!(chip.call_stack().is_empty());
+    }
+
+    /// Run an 8XYN instruction with `x` in VX and `y` in VY, returning VX and VF.
+    fn run_alu(
+        mode: CompatabilityMode,
+        instruction: fn(u8, u8) -> Instruction,
+        x: u8,
+        y: u8,
+    ) -> (u8, u8) {
+        let mut chip = chip8(&[], mode);
+        chip.var_registers[0x1] = x;
+        chip.var_registers[0x2] = y;
+        chip.var_registers[0xF] = 0xAA;
+
+        run(&mut chip, instruction(0x1, 0x2));
+
+        (chip.var_registers[0x1], chip.var_registers[0xF])
+    }
+
+    /// Run an 8XYN instruction where VX is VF, returning VF.
+    fn run_alu_into_vf(instruction: fn(u8, u8) -> Instruction, x: u8, y: u8) -> u8 {
+        let mut chip = chip8(&[], CompatabilityMode::Cosmac);
+        chip.var_registers[0xF] = x;
+        chip.var_registers[0x2] = y;
+
+        run(&mut chip, instruction(0xF, 0x2));
+
+        chip.var_registers[0xF]
+    }
+
+    const fn or(register_x: u8, register_y: u8) -> Instruction {
+        Instruction::Or {
+            register_x,
+            register_y,
+        }
+    }
+
+    const fn and(register_x: u8, register_y: u8) -> Instruction {
+        Instruction::And {
+            register_x,
+            register_y,
+        }
+    }
+
+    const fn xor(register_x: u8, register_y: u8) -> Instruction {
+        Instruction::Xor {
+            register_x,
+            register_y,
+        }
+    }
+
+    const fn add(register_x: u8, register_y: u8) -> Instruction {
+        Instruction::Add {
+            register_x,
+            register_y,
+        }
+    }
+
+    const fn subtract_right(register_x: u8, register_y: u8) -> Instruction {
+        Instruction::SubtractRight {
+            register_x,
+            register_y,
+        }
+    }
+
+    const fn subtract_left(register_x: u8, register_y: u8) -> Instruction {
+        Instruction::SubtractLeft {
+            register_x,
+            register_y,
+        }
+    }
+
+    const fn shift_right(register_x: u8, register_y: u8) -> Instruction {
+        Instruction::ShiftRight {
+            register_x,
+            register_y,
+        }
+    }
+
+    const fn shift_left(register_x: u8, register_y: u8) -> Instruction {
+        Instruction::ShiftLeft {
+            register_x,
+            register_y,
+        }
+    }
+
+    #[test]
+    fn test_logic_resets_vf() {
+        let mode = CompatabilityMode::Cosmac;
+
+        assert_eq!(run_alu(mode, or, 0b1100, 0b1010), (0b1110, 0));
+        assert_eq!(run_alu(mode, and, 0b1100, 0b1010), (0b1000, 0));
+        assert_eq!(run_alu(mode, xor, 0b1100, 0b1010), (0b0110, 0));
+    }
+
+    #[test]
+    fn test_logic_keeps_vf() {
+        let mode = CompatabilityMode::Super;
+
+        assert_eq!(run_alu(mode, or, 0b1100, 0b1010), (0b1110, 0xAA));
+        assert_eq!(run_alu(mode, and, 0b1100, 0b1010), (0b1000, 0xAA));
+        assert_eq!(run_alu(mode, xor, 0b1100, 0b1010), (0b0110, 0xAA));
+    }
+
+    #[test]
+    fn test_add() {
+        let mode = CompatabilityMode::Cosmac;
+
+        assert_eq!(run_alu(mode, add, 0x10, 0x20), (0x30, 0));
+        assert_eq!(run_alu(mode, add, 0xFF, 0x01), (0x00, 1));
+        assert_eq!(run_alu(mode, add, 0xF0, 0x20), (0x10, 1));
+    }
+
+    #[test]
+    fn test_subtract_right() {
+        let mode = CompatabilityMode::Cosmac;
+
+        assert_eq!(run_alu(mode, subtract_right, 0x30, 0x10), (0x20, 1));
+        assert_eq!(run_alu(mode, subtract_right, 0x10, 0x10), (0x00, 1));
+        assert_eq!(run_alu(mode, subtract_right, 0x10, 0x30), (0xE0, 0));
+    }
+
+    #[test]
+    fn test_subtract_left() {
+        let mode = CompatabilityMode::Cosmac;
+
+        assert_eq!(run_alu(mode, subtract_left, 0x10, 0x30), (0x20, 1));
+        assert_eq!(run_alu(mode, subtract_left, 0x10, 0x10), (0x00, 1));
+        assert_eq!(run_alu(mode, subtract_left, 0x30, 0x10), (0xE0, 0));
+    }
+
+    #[test]
+    fn test_shift_right() {
+        // COSMAC and XO-CHIP shift VY, SUPER-CHIP shifts VX.
+        assert_eq!(
+            run_alu(CompatabilityMode::Cosmac, shift_right, 0x00, 0b0000_0011),
+            (0b0000_0001, 1)
+        );
+        assert_eq!(
+            run_alu(CompatabilityMode::Xo, shift_right, 0x00, 0b0000_0010),
+            (0b0000_0001, 0)
+        );
+        assert_eq!(
+            run_alu(CompatabilityMode::Super, shift_right, 0b0000_0101, 0x00),
+            (0b0000_0010, 1)
+        );
+    }
+
+    #[test]
+    fn test_shift_left() {
+        assert_eq!(
+            run_alu(CompatabilityMode::Cosmac, shift_left, 0x00, 0b1100_0000),
+            (0b1000_0000, 1)
+        );
+        assert_eq!(
+            run_alu(CompatabilityMode::Xo, shift_left, 0x00, 0b0100_0000),
+            (0b1000_0000, 0)
+        );
+        assert_eq!(
+            run_alu(CompatabilityMode::Super, shift_left, 0b1010_0000, 0x00),
+            (0b0100_0000, 1)
+        );
+    }
+
+    #[test]
+    fn test_flag_overwrites_result_in_vf() {
+        assert_eq!(run_alu_into_vf(add, 0xFF, 0x02), 1);
+        assert_eq!(run_alu_into_vf(add, 0x01, 0x02), 0);
+        assert_eq!(run_alu_into_vf(subtract_right, 0x01, 0x02), 0);
+        assert_eq!(run_alu_into_vf(subtract_left, 0x01, 0x02), 1);
+        assert_eq!(run_alu_into_vf(shift_right, 0x00, 0b0000_0001), 1);
+        assert_eq!(run_alu_into_vf(shift_left, 0x00, 0b0111_1111), 0);
+    }
+
+    /// Draw `sprite` at `column` and `row` onto the display of `chip`.
+    fn blit(chip: &mut Chip8, column: u8, row: u8, sprite: &[u8]) {
+        chip.memory[0x300..0x300 + sprite.len()].copy_from_slice(sprite);
+        chip.index_register = 0x300;
+        chip.var_registers[0x0] = column;
+        chip.var_registers[0x1] = row;
+
+        run(
+            chip,
+            Instruction::Display {
+                x_coord_register: 0x0,
+                y_coord_register: 0x1,
+                sprite_height:    u8::try_from(sprite.len()).unwrap(),
+            },
+        );
+    }
+
+    /// Draw `sprite` at `column` and `row` onto a blank display, returning the display.
+    fn draw_sprite(
+        compatibility_mode: CompatabilityMode,
+        column: u8,
+        row: u8,
+        sprite: &[u8],
+    ) -> Display {
+        let mut chip = chip8(&[], compatibility_mode);
+
+        blit(&mut chip, column, row, sprite);
+
+        chip.display
+    }
+
+    #[test]
+    fn test_sprite_xors_onto_display() {
+        let mut chip = chip8(&[], CompatabilityMode::Cosmac);
+
+        blit(&mut chip, 0, 0, &[0b1100_0000]);
+        assert_eq!(chip.var_registers[0xF], 0);
+
+        blit(&mut chip, 0, 0, &[0b1010_0000]);
+        assert_eq!(chip.display.row(0)[..4], [false, true, true, false]);
+        assert_eq!(chip.var_registers[0xF], 1);
+    }
+
+    #[test]
+    fn test_redrawing_sprite_erases_it() {
+        let mut chip = chip8(&[], CompatabilityMode::Cosmac);
+
+        blit(&mut chip, 10, 5, &[0xF0, 0x90, 0xF0]);
+        blit(&mut chip, 10, 5, &[0xF0, 0x90, 0xF0]);
+
+        assert_eq!(chip.display, Display::default());
+        assert_eq!(chip.var_registers[0xF], 1);
+    }
+
+    #[test]
+    fn test_blank_sprite_bits_leave_display_alone() {
+        let mut chip = chip8(&[], CompatabilityMode::Cosmac);
+
+        blit(&mut chip, 0, 0, &[0xFF]);
+        blit(&mut chip, 0, 0, &[0x00]);
+
+        assert_eq!(chip.display.row(0)[..8], [true; 8]);
+        assert_eq!(chip.var_registers[0xF], 0);
+    }
+
+    #[test]
+    fn test_clipped_pixels_do_not_collide() {
+        let mut chip = chip8(&[], CompatabilityMode::Cosmac);
+
+        blit(&mut chip, 0, 0, &[0x80]);
+        blit(&mut chip, 60, 0, &[0x0F]);
+
+        assert!(chip.display.pixel(0, 0));
+        assert_eq!(chip.var_registers[0xF], 0);
+
+        blit(&mut chip, 0, 31, &[0x40, 0x80]);
+
+        assert!(chip.display.pixel(0, 0));
+        assert_eq!(chip.var_registers[0xF], 0);
+    }
+
+    #[test]
+    fn test_sprite_clips_at_right_edge() {
+        let display = draw_sprite(CompatabilityMode::Super, 60, 0, &[0xFF]);
+
+        assert_eq!(display.row(0)[60..], [true; 4]);
+        assert_eq!(display.row(0)[..4], [false; 4]);
+    }
+
+    #[test]
+    fn test_sprite_clips_at_bottom_edge() {
+        let mut chip = chip8(&[], CompatabilityMode::Cosmac);
+
+        blit(&mut chip, 0, 31, &[0x80, 0x80]);
+
+        assert!(chip.display.pixel(0, 31));
+        assert!(!chip.display.pixel(0, 0));
+        assert_eq!(chip.var_registers[0xF], 0);
+    }
+
+    #[test]
+    fn test_sprite_wraps_at_right_edge() {
+        let display = draw_sprite(CompatabilityMode::Xo, 60, 0, &[0xFF]);
+
+        assert_eq!(display.row(0)[60..], [true; 4]);
+        assert_eq!(display.row(0)[..4], [true; 4]);
+    }
+
+    #[test]
+    fn test_sprite_wraps_at_bottom_edge() {
+        let display = draw_sprite(CompatabilityMode::Xo, 0, 31, &[0x80, 0x80]);
+
+        assert!(display.pixel(0, 31));
+        assert!(display.pixel(0, 0));
+    }
+
+    #[test]
+    fn test_sprite_start_position_wraps() {
+        // Coordinates past the edge always wrap, only the sprite itself is clipped.
+        let display = draw_sprite(CompatabilityMode::Cosmac, 64 + 2, 32 + 3, &[0x80]);
+
+        assert!(display.pixel(2, 3));
+    }
+
+    #[test]
+    fn test_high_resolution() {
+        let mut chip = chip8(&[], CompatabilityMode::Super);
+
+        blit(&mut chip, 0, 0, &[0x80]);
+        run(&mut chip, Instruction::HighResolution);
+
+        assert_eq!(chip.display.width(), 128);
+        assert_eq!(chip.display.height(), 64);
+        assert!(!chip.display.pixel(0, 0));
+
+        blit(&mut chip, 100, 50, &[0x80]);
+        assert!(chip.display.pixel(100, 50));
+
+        run(&mut chip, Instruction::LowResolution);
+        assert_eq!(chip.display.width(), 64);
+        assert_eq!(chip.display, Display::default());
+    }
+
+    #[test]
+    fn test_resolution_unsupported_on_cosmac() {
+        let program = [0x00, 0xFF];
+        let mut chip = chip8(&program, CompatabilityMode::Cosmac);
+
+        let error = chip.step().unwrap_err().downcast::<Error>();
+
+        assert!(matches!(error.unwrap(), Error::UnsupportedOpcode { .. }));
+    }
+
+    #[test]
+    fn test_large_font_needs_super_chip() {
+        let program = [0xF0, 0x30];
+        let mut chip = chip8(&program, CompatabilityMode::Cosmac);
+
+        let error = chip.step().unwrap_err().downcast::<Error>();
+
+        assert!(matches!(error.unwrap(), Error::UnsupportedOpcode { .. }));
+    }
+
+    #[test]
+    fn test_scroll_down() {
+        let mut chip = chip8(&[], CompatabilityMode::Xo);
+
+        blit(&mut chip, 0, 0, &[0x80]);
+        run(&mut chip, Instruction::ScrollDown(3));
+
+        assert!(!chip.display.pixel(0, 0));
+        assert!(chip.display.pixel(0, 3));
+    }
+
+    #[test]
+    fn test_scroll_up() {
+        let mut chip = chip8(&[], CompatabilityMode::Xo);
+
+        blit(&mut chip, 0, 5, &[0x80]);
+        run(&mut chip, Instruction::SelectPlanes(2));
+        blit(&mut chip, 1, 5, &[0x80]);
+        run(&mut chip, Instruction::ScrollUp(3));
+
+        assert!(chip.display.pixel(0, 5));
+        assert!(!chip.display.pixel(1, 5));
+        assert_eq!(chip.display.planes(1, 2), 0b10);
+    }
+
+    #[test]
+    fn test_scroll_up_needs_xo_chip() {
+        let program = [0x00, 0xD1];
+
+        for compatibility_mode in [CompatabilityMode::Cosmac, CompatabilityMode::Super] {
+            let mut chip = chip8(&program, compatibility_mode);
+
+            let error = chip.step().unwrap_err().downcast::<Error>();
+
+            assert!(matches!(error.unwrap(), Error::UnsupportedOpcode { .. }));
+        }
+    }
+
+    #[test]
+    fn test_scroll_sideways() {
+        let mut chip = chip8(&[], CompatabilityMode::Xo);
+
+        blit(&mut chip, 10, 0, &[0x80]);
+        run(&mut chip, Instruction::ScrollRight);
+        assert!(chip.display.pixel(14, 0));
+
+        run(&mut chip, Instruction::ScrollLeft);
+        run(&mut chip, Instruction::ScrollLeft);
+        assert!(chip.display.pixel(6, 0));
+        assert_eq!(
+            chip.display.row(0).iter().filter(|pixel| **pixel).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_scroll_drops_pixels_past_edge() {
+        let mut chip = chip8(&[], CompatabilityMode::Xo);
+
+        blit(&mut chip, 62, 31, &[0xC0]);
+        run(&mut chip, Instruction::ScrollRight);
+        run(&mut chip, Instruction::ScrollDown(1));
+
+        assert_eq!(chip.display, Display::default());
+    }
+
+    #[test]
+    fn test_draw_on_both_planes() {
+        let mut chip = chip8(&[], CompatabilityMode::Xo);
+
+        // First plane's row followed by the second plane's row.
+        chip.memory[0x300..0x302].copy_from_slice(&[0xC0, 0xA0]);
+        chip.index_register = 0x300;
+
+        run(&mut chip, Instruction::SelectPlanes(3));
+        run(
+            &mut chip,
+            Instruction::Display {
+                x_coord_register: 0x0,
+                y_coord_register: 0x0,
+                sprite_height:    1,
+            },
+        );
+
+        assert_eq!(chip.display.planes(0, 0), 0b11);
+        assert_eq!(chip.display.planes(1, 0), 0b01);
+        assert_eq!(chip.display.planes(2, 0), 0b10);
+        assert_eq!(chip.var_registers[0xF], 0);
+
+        // Only the second plane collides.
+        run(&mut chip, Instruction::SelectPlanes(2));
+        blit(&mut chip, 2, 0, &[0x80]);
+
+        assert_eq!(chip.display.planes(2, 0), 0);
+        assert_eq!(chip.var_registers[0xF], 1);
+    }
+
+    #[test]
+    fn test_clear_and_scroll_selected_planes() {
+        let mut chip = chip8(&[], CompatabilityMode::Xo);
+
+        blit(&mut chip, 0, 0, &[0x80]);
+        run(&mut chip, Instruction::SelectPlanes(2));
+        blit(&mut chip, 0, 0, &[0x80]);
+
+        run(&mut chip, Instruction::ScrollDown(1));
+
+        assert_eq!(chip.display.planes(0, 0), 0b01);
+        assert_eq!(chip.display.planes(0, 1), 0b10);
+
+        run(&mut chip, Instruction::ClearScreen);
+
+        assert_eq!(chip.display.planes(0, 0), 0b01);
+        assert_eq!(chip.display.planes(0, 1), 0);
+    }
+
+    #[test]
+    fn test_half_pixel_scroll_in_low_resolution() {
+        let mut chip = chip8(&[], CompatabilityMode::Super);
+
+        blit(&mut chip, 10, 0, &[0x80]);
+        run(&mut chip, Instruction::ScrollDown(4));
+        run(&mut chip, Instruction::ScrollRight);
+        assert!(chip.display.pixel(12, 2));
+
+        run(&mut chip, Instruction::HighResolution);
+        blit(&mut chip, 10, 0, &[0x80]);
+        run(&mut chip, Instruction::ScrollDown(4));
+        run(&mut chip, Instruction::ScrollRight);
+        assert!(chip.display.pixel(14, 4));
+    }
+
+    #[test]
+    fn test_half_pixel_scroll_rounds_up() {
+        let mut chip = chip8(&[], CompatabilityMode::Super);
+
+        blit(&mut chip, 0, 0, &[0x80]);
+        run(&mut chip, Instruction::ScrollDown(1));
+        assert!(chip.display.pixel(0, 1));
+
+        run(&mut chip, Instruction::ScrollDown(3));
+        assert!(chip.display.pixel(0, 3));
+    }
+
+    #[test]
+    fn test_large_sprite() {
+        let mut chip = chip8(&[], CompatabilityMode::Xo);
+        run(&mut chip, Instruction::HighResolution);
+
+        let mut sprite = [0; 32];
+        sprite[0] = 0x80;
+        sprite[31] = 0x01;
+        chip.memory[0x300..0x320].copy_from_slice(&sprite);
+        chip.index_register = 0x300;
+
+        run(
+            &mut chip,
+            Instruction::Display {
+                x_coord_register: 0x0,
+                y_coord_register: 0x0,
+                sprite_height:    0,
+            },
+        );
+
+        assert!(chip.display.pixel(0, 0));
+        assert!(chip.display.pixel(15, 15));
+        assert_eq!(
+            chip.display
+                .buffer()
+                .iter()
+                .filter(|pixel| **pixel != 0)
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_count_collided_rows() {
+        let mut chip = chip8(&[], CompatabilityMode::Super);
+        run(&mut chip, Instruction::HighResolution);
+
+        blit(&mut chip, 0, 0, &[0x80, 0x00, 0x80]);
+        blit(&mut chip, 0, 0, &[0x80, 0x80, 0x80]);
+        assert_eq!(chip.var_registers[0xF], 2);
+
+        // Rows clipped at the bottom edge count as collisions.
+        blit(&mut chip, 0, 62, &[0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(chip.var_registers[0xF], 2);
+
+        run(&mut chip, Instruction::LowResolution);
+        blit(&mut chip, 0, 0, &[0x80, 0x80]);
+        blit(&mut chip, 0, 0, &[0x80, 0x80]);
+        assert_eq!(chip.var_registers[0xF], 1);
+    }
+
+    #[test]
+    fn test_large_font_character() {
+        let mut chip = chip8(&[], CompatabilityMode::Super);
+        chip.var_registers[0x2] = 0x9;
+
+        run(&mut chip, Instruction::LargeFontCharacter { register: 0x2 });
+
+        let address = chip.index_register as usize;
+        assert_eq!(chip.memory[address..address + 10], LARGE_FONT[90..100]);
+    }
+
+    #[test]
+    fn test_exit_ends_run() {
+        // Save V0 to the flags then exit in the same frame.
+        let program = [0x60, 0x2A, 0xF0, 0x75, 0x00, 0xFD];
+        let mut flags = RecordingFlags::default();
+
+        let mut ui = ScriptedUi {
+            keys:  [false; KEY_COUNT],
+            draws: 1,
+        };
+
+        let mut chip = chip8(&program, CompatabilityMode::Super);
+
+        chip.run(&mut ui, &mut Silent, &mut flags).unwrap();
+
+        assert_eq!(flags.flags[0], 0x2A);
+        assert_eq!(ui.draws, 1);
+    }
+
+    #[test]
+    fn test_flags_kept_between_runs() {
+        // Save V0 to V2 to the flags then loop forever.
+        let program = [0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0xF2, 0x75, 0x12, 0x08];
+        let mut flags = RecordingFlags::default();
+
+        let mut ui = ScriptedUi {
+            keys:  [false; KEY_COUNT],
+            draws: 2,
+        };
+
+        let mut chip = chip8(&program, CompatabilityMode::Super);
+
+        assert!(chip.run(&mut ui, &mut Silent, &mut flags).is_err());
+        assert_eq!(flags.flags[..4], [0x01, 0x02, 0x03, 0x00]);
+        assert_eq!(flags.saves, 1);
+
+        // Load V0 to V2 from the flags then loop forever.
+        let program = [0xF2, 0x85, 0x12, 0x02];
+        let mut chip = chip8(&program, CompatabilityMode::Super);
+
+        assert!(chip.run(&mut ui, &mut Silent, &mut flags).is_err());
+        assert_eq!(chip.var_registers[..3], [0x01, 0x02, 0x03]);
+    }
+
+    #[test]
+    fn test_flags_saved_before_error() {
+        // Save V0 to the flags then crash on an unknown opcode in the same frame.
+        let program = [0x60, 0x2A, 0xF0, 0x75, 0xFF, 0xFF];
+        let mut flags = RecordingFlags::default();
+
+        let mut ui = ScriptedUi {
+            keys:  [false; KEY_COUNT],
+            draws: 1,
+        };
+
+        let mut chip = chip8(&program, CompatabilityMode::Super);
+
+        assert!(chip.run(&mut ui, &mut Silent, &mut flags).is_err());
+        assert_eq!(flags.flags[0], 0x2A);
+        assert_eq!(flags.saves, 1);
+    }
+
+    #[test]
+    fn test_super_chip_has_eight_flags() {
+        let program = [0xF8, 0x75];
+        let mut chip = chip8(&program, CompatabilityMode::Super);
+
+        let error = chip.step().unwrap_err().downcast::<Error>();
+
+        assert!(matches!(error.unwrap(), Error::UnsupportedOpcode { .. }));
+    }
+
+    #[test]
+    fn test_timers_stop_at_zero() {
+        let mut chip = chip8(&[], CompatabilityMode::Cosmac);
+        chip.sound_timer = 1;
+
+        chip.tick_timers();
+        chip.tick_timers();
+
+        assert_eq!(chip.sound_timer, 0);
+    }
+
+    #[test]
+    fn test_get_key_waits_for_release() {
+        let mut chip = chip8(&[], CompatabilityMode::Cosmac);
+        chip.program_counter = 0x202;
+
+        let mut keys = [false; KEY_COUNT];
+        keys[0x7] = true;
+        chip.set_keys(keys);
+
+        run(&mut chip, Instruction::GetKey { register: 0x3 });
+        assert_eq!(chip.program_counter, 0x200);
+
+        chip.program_counter = 0x202;
+        chip.set_keys([false; KEY_COUNT]);
+
+        run(&mut chip, Instruction::GetKey { register: 0x3 });
+        assert_eq!(chip.program_counter, 0x202);
+        assert_eq!(chip.var_registers[0x3], 0x7);
+
+        // The same release does not satisfy a second wait.
+        run(&mut chip, Instruction::GetKey { register: 0x4 });
+        assert_eq!(chip.program_counter, 0x200);
+        assert_eq!(chip.var_registers[0x4], 0);
+    }
+
+    #[test]
+    fn test_get_key_wraps_program_counter() {
+        let mut chip = chip8(&[], CompatabilityMode::Xo);
+        chip.program_counter = 0;
+
+        run(&mut chip, Instruction::GetKey { register: 0x3 });
+
+        assert_eq!(chip.program_counter, 0xFFFE);
+    }
+
+    #[test]
+    fn test_delay_timer() {
+        let mut chip = chip8(&[], CompatabilityMode::Cosmac);
+        chip.var_registers[0x4] = 0x20;
+
+        run(&mut chip, Instruction::SetDelayTimer { register: 0x4 });
+        chip.tick_timers();
+        run(&mut chip, Instruction::GetDelayTimer { register: 0x5 });
+
+        assert_eq!(chip.var_registers[0x5], 0x1F);
+    }
+
+    #[test]
+    fn test_add_to_index() {
+        let mut chip = chip8(&[], CompatabilityMode::Cosmac);
+        chip.index_register = 0x300;
+        chip.var_registers[0x0] = 0x12;
+
+        run(&mut chip, Instruction::AddToIndex { register: 0x0 });
+
+        assert_eq!(chip.index_register, 0x312);
+    }
+
+    #[test]
+    fn test_font_character() {
+        let mut chip = chip8(&[], CompatabilityMode::Cosmac);
+        // Only the low nibble picks the character.
+        chip.var_registers[0x1] = 0xFA;
+
+        run(&mut chip, Instruction::FontCharacter { register: 0x1 });
+
+        let address = chip.index_register as usize;
+        assert_eq!(address, 0x050 + 0xA * 5);
+        assert_eq!(chip.memory[address..address + 5], FONT[50..55]);
+    }
+
+    #[test]
+    fn test_decimal_conversion() {
+        let mut chip = chip8(&[], CompatabilityMode::Cosmac);
+        chip.index_register = 0x300;
+        chip.var_registers[0x2] = 254;
+
+        run(&mut chip, Instruction::DecimalConversion { register: 0x2 });
+
+        assert_eq!(chip.memory[0x300..0x303], [2, 5, 4]);
+        assert_eq!(chip.index_register, 0x300);
+    }
+
+    #[test]
+    fn test_store_and_load_memory() {
+        for (compatibility_mode, index_after) in [
+            (CompatabilityMode::Cosmac, 0x303),
+            (CompatabilityMode::Super, 0x300),
+            (CompatabilityMode::Xo, 0x300),
+        ] {
+            let mut chip = chip8(&[], compatibility_mode);
+            chip.index_register = 0x300;
+            chip.var_registers[..4].copy_from_slice(&[1, 2, 3, 4]);
+
+            run(&mut chip, Instruction::StoreMemory { register: 0x2 });
+
+            assert_eq!(chip.memory[0x300..0x304], [1, 2, 3, 0]);
+            assert_eq!(chip.index_register, index_after);
+
+            chip.index_register = 0x300;
+            chip.var_registers = [0; 16];
+
+            run(&mut chip, Instruction::LoadMemory { register: 0x2 });
+
+            assert_eq!(chip.var_registers[..4], [1, 2, 3, 0]);
+            assert_eq!(chip.index_register, index_after);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+/// Size of the display in low resolution mode.
+pub const LORES_WIDTH: usize = 64;
+pub const LORES_HEIGHT: usize = 32;
+
+/// Size of the display in SUPER-CHIP high resolution mode.
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+
+/// Amount of XO-CHIP bitplanes.
+pub const PLANE_COUNT: usize = 2;
+
+/// Mask selecting every plane.
+pub const ALL_PLANES: u8 = (1 << PLANE_COUNT) - 1;
+
+/// Amount of colours the planes can combine into.
+pub const COLOR_COUNT: usize = 1 << PLANE_COUNT;
+
+/// Display of one or more bitplanes that switches between low and high resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Display {
+    /// Pixels going from left to right, top to bottom.
+    ///
+    /// Each pixel is a mask of the planes it is lit on, the first plane being the lowest bit.
+    /// Rows are as wide as the current resolution, any pixels past the current
+    /// resolution are unused.
+    pixels: [u8; HIRES_WIDTH * HIRES_HEIGHT],
+    hires:  bool,
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Self {
+            pixels: [0; HIRES_WIDTH * HIRES_HEIGHT],
+            hires:  false,
+        }
+    }
+}
+
+impl Display {
+    pub const fn width(&self) -> usize {
+        if self.hires {
+            HIRES_WIDTH
+        } else {
+            LORES_WIDTH
+        }
+    }
+
+    pub const fn height(&self) -> usize {
+        if self.hires {
+            HIRES_HEIGHT
+        } else {
+            LORES_HEIGHT
+        }
+    }
+
+    pub const fn is_hires(&self) -> bool { self.hires }
+
+    /// Turn off every pixel on `planes`.
+    pub fn clear(&mut self, planes: u8) {
+        for pixel in &mut self.pixels {
+            *pixel &= !planes;
+        }
+    }
+
+    /// Switch between low and high resolution, clearing every plane.
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.clear(ALL_PLANES);
+    }
+
+    /// Pixels of the current resolution going from left to right, top to bottom.
+    pub fn buffer(&self) -> &[u8] { &self.pixels[..self.width() * self.height()] }
+
+    /// Move every pixel on `planes` down by `amount` rows, leaving blank rows at the top.
+    pub fn scroll_down(&mut self, amount: usize, planes: u8) {
+        let width = self.width();
+
+        for row in (0..self.height()).rev() {
+            for column in 0..width {
+                let source = row.checked_sub(amount).map(|row| row * width + column);
+
+                self.move_pixel(row * width + column, source, planes);
+            }
+        }
+    }
+
+    /// Move every pixel on `planes` up by `amount` rows, leaving blank rows at the bottom.
+    pub fn scroll_up(&mut self, amount: usize, planes: u8) {
+        let width = self.width();
+        let height = self.height();
+
+        for row in 0..height {
+            for column in 0..width {
+                let source = Some(row + amount)
+                    .filter(|row| *row < height)
+                    .map(|row| row * width + column);
+
+                self.move_pixel(row * width + column, source, planes);
+            }
+        }
+    }
+
+    /// Move every pixel on `planes` right by `amount` columns, leaving blank columns on the left.
+    pub fn scroll_right(&mut self, amount: usize, planes: u8) {
+        let width = self.width();
+
+        for row in 0..self.height() {
+            for column in (0..width).rev() {
+                let source = column
+                    .checked_sub(amount)
+                    .map(|column| row * width + column);
+
+                self.move_pixel(row * width + column, source, planes);
+            }
+        }
+    }
+
+    /// Move every pixel on `planes` left by `amount` columns, leaving blank columns on the right.
+    pub fn scroll_left(&mut self, amount: usize, planes: u8) {
+        let width = self.width();
+
+        for row in 0..self.height() {
+            for column in 0..width {
+                let source = Some(column + amount)
+                    .filter(|column| *column < width)
+                    .map(|column| row * width + column);
+
+                self.move_pixel(row * width + column, source, planes);
+            }
+        }
+    }
+
+    /// Replace the pixel at `index` on `planes` with the one at `source`,
+    /// or turn it off if the source is off screen.
+    fn move_pixel(&mut self, index: usize, source: Option<usize>, planes: u8) {
+        let moved = source.map_or(0, |source| self.pixels[source] & planes);
+
+        self.pixels[index] = (self.pixels[index] & !planes) | moved;
+    }
+
+    /// Whether the pixel at `column` and `row` is lit on any plane.
+    #[cfg(test)]
+    pub const fn pixel(&self, column: usize, row: usize) -> bool { self.planes(column, row) != 0 }
+
+    /// Mask of the planes the pixel at `column` and `row` is lit on.
+    #[cfg(test)]
+    pub const fn planes(&self, column: usize, row: usize) -> u8 {
+        self.pixels[row * self.width() + column]
+    }
+
+    /// Whether each pixel of a single row of the current resolution is lit on any plane.
+    #[cfg(test)]
+    pub fn row(&self, row: usize) -> Vec<bool> {
+        let width = self.width();
+
+        self.pixels[row * width..(row + 1) * width]
+            .iter()
+            .map(|pixel| *pixel != 0)
+            .collect()
+    }
+
+    /// Flip the pixel at `column` and `row` on a single `plane`,
+    /// returning whether it was lit on that plane.
+    pub const fn toggle(&mut self, column: usize, row: usize, plane: u8) -> bool {
+        let index = row * self.width() + column;
+        let lit = self.pixels[index] & plane != 0;
+
+        self.pixels[index] ^= plane;
+
+        lit
+    }
+}
@@ -0,0 +1,50 @@
+use thiserror::Error;
+
+use crate::arguments::compatability_mode::CompatabilityMode;
+
+/// Reasons a program can fail while being emulated.
+///
+/// Every error carries the address of the instruction that caused it.
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    #[error("Unknown opcode {opcode:04X} at {program_counter:#05X}")]
+    UnknownOpcode {
+        opcode:          u16,
+        program_counter: u16,
+    },
+
+    #[error(
+        "Opcode {opcode:04X} at {program_counter:#05X} is not supported in \
+         {compatibility_mode:?} compatibility mode"
+    )]
+    UnsupportedOpcode {
+        opcode:             u16,
+        program_counter:    u16,
+        compatibility_mode: CompatabilityMode,
+    },
+
+    #[error("Program counter {program_counter:#05X} is outside of memory")]
+    ProgramCounterOutOfBounds { program_counter: u16 },
+
+    #[error("Stack overflow by opcode {opcode:04X} at {program_counter:#05X}")]
+    StackOverflow {
+        opcode:          u16,
+        program_counter: u16,
+    },
+
+    #[error("Stack underflow by opcode {opcode:04X} at {program_counter:#05X}")]
+    StackUnderflow {
+        opcode:          u16,
+        program_counter: u16,
+    },
+
+    #[error(
+        "Opcode {opcode:04X} at {program_counter:#05X} accessed memory outside of range at \
+         {address:#05X}"
+    )]
+    MemoryOutOfBounds {
+        opcode:          u16,
+        program_counter: u16,
+        address:         usize,
+    },
+}
@@ -1,11 +1,32 @@
-use anyhow::{bail, Ok};
+use thiserror::Error;
+
+use crate::arguments::compatability_mode::CompatabilityMode;
+
+/// Opcode that does not decode to any instruction.
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+#[error("Unknown opcode {0:04X}")]
+pub struct UnknownOpcode(pub u16);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Instruction {
+    /// 00CN
+    ScrollDown(u8),
+    /// 00DN
+    ScrollUp(u8),
     /// 00E0
     ClearScreen,
     /// 00EE
     Return,
+    /// 00FB
+    ScrollRight,
+    /// 00FC
+    ScrollLeft,
+    /// 00FD
+    Exit,
+    /// 00FE
+    LowResolution,
+    /// 00FF
+    HighResolution,
     /// 1NNN
     Goto(u16),
     /// 2NNN
@@ -16,6 +37,10 @@ pub enum Instruction {
     NotEqualVal { register: u8, value: u8 },
     /// 5XY0
     IsEqual { register_x: u8, register_y: u8 },
+    /// 5XY2
+    StoreRange { register_x: u8, register_y: u8 },
+    /// 5XY3
+    LoadRange { register_x: u8, register_y: u8 },
     /// 6XNN
     SetVal { register: u8, value: u8 },
     /// 7XNN
@@ -33,15 +58,17 @@ pub enum Instruction {
     /// 8XY5
     SubtractRight { register_x: u8, register_y: u8 },
     /// 8XY6
-    ShiftLeft { register_x: u8, register_y: u8 },
+    ShiftRight { register_x: u8, register_y: u8 },
     /// 8XY7
     SubtractLeft { register_x: u8, register_y: u8 },
     /// 8XYE
-    ShiftRight { register_x: u8, register_y: u8 },
+    ShiftLeft { register_x: u8, register_y: u8 },
     /// 9XY0
     NotEqual { register_x: u8, register_y: u8 },
     /// ANNN
     SetIndexRegister(u16),
+    /// BNNN
+    GotoOffset(u16),
     /// CXNN
     Rand { register: u8, value: u8 },
     /// DXYN
@@ -53,13 +80,57 @@ pub enum Instruction {
         // N
         sprite_height:    u8,
     },
+    /// EX9E
+    IsKeyPressed { register: u8 },
+    /// EXA1
+    NotKeyPressed { register: u8 },
+    /// F000 NNNN, the address is the following two bytes.
+    SetLongIndexRegister,
+    /// FN01
+    SelectPlanes(u8),
+    /// F002
+    LoadAudioPattern,
+    /// FX07
+    GetDelayTimer { register: u8 },
+    /// FX0A
+    GetKey { register: u8 },
+    /// FX15
+    SetDelayTimer { register: u8 },
+    /// FX18
+    SetSoundTimer { register: u8 },
+    /// FX1E
+    AddToIndex { register: u8 },
+    /// FX29
+    FontCharacter { register: u8 },
+    /// FX30
+    LargeFontCharacter { register: u8 },
+    /// FX33
+    DecimalConversion { register: u8 },
+    /// FX3A
+    SetPitch { register: u8 },
+    /// FX55
+    StoreMemory { register: u8 },
+    /// FX65
+    LoadMemory { register: u8 },
+    /// FX75
+    SaveFlags { register: u8 },
+    /// FX85
+    LoadFlags { register: u8 },
 }
 
 impl TryFrom<u16> for Instruction {
-    type Error = anyhow::Error;
+    type Error = UnknownOpcode;
 
     #[allow(clippy::too_many_lines)]
     fn try_from(value: u16) -> Result<Self, Self::Error> {
+        if value & 0xFFF0 == 0x00C0 {
+            return Ok(Self::ScrollDown((value & 0xF) as u8));
+        }
+
+        if value & 0xFFF0 == 0x00D0 {
+            return Ok(Self::ScrollUp((value & 0xF) as u8));
+        }
+
         if value == 0x00E0 {
             return Ok(Self::ClearScreen);
         }
@@ -68,6 +139,26 @@ impl TryFrom<u16> for Instruction {
             return Ok(Self::Return);
         }
 
+        if value == 0x00FB {
+            return Ok(Self::ScrollRight);
+        }
+
+        if value == 0x00FC {
+            return Ok(Self::ScrollLeft);
+        }
+
+        if value == 0x00FD {
+            return Ok(Self::Exit);
+        }
+
+        if value == 0x00FE {
+            return Ok(Self::LowResolution);
+        }
+
+        if value == 0x00FF {
+            return Ok(Self::HighResolution);
+        }
+
         if value & 0xF000 == 0x1000 {
             return Ok(Self::Goto(value & 0x0FFF));
         }
@@ -97,6 +188,20 @@ impl TryFrom<u16> for Instruction {
             });
         }
 
+        if value & 0xF00F == 0x5002 {
+            return Ok(Self::StoreRange {
+                register_x: ((value >> 8) & 0xF) as u8,
+                register_y: ((value >> 4) & 0xF) as u8,
+            });
+        }
+
+        if value & 0xF00F == 0x5003 {
+            return Ok(Self::LoadRange {
+                register_x: ((value >> 8) & 0xF) as u8,
+                register_y: ((value >> 4) & 0xF) as u8,
+            });
+        }
+
         if value & 0xF000 == 0x6000 {
             return Ok(Self::SetVal {
                 register: ((value >> 8) & 0xF) as u8,
@@ -128,6 +233,10 @@ impl TryFrom<u16> for Instruction {
             return Ok(Self::SetIndexRegister(value & 0x0FFF));
         }
 
+        if value & 0xF000 == 0xB000 {
+            return Ok(Self::GotoOffset(value & 0x0FFF));
+        }
+
         if value & 0xF000 == 0xC000 {
             return Ok(Self::Rand {
                 register: ((value >> 8) & 0xF) as u8,
@@ -154,12 +263,73 @@ impl TryFrom<u16> for Instruction {
             });
         }
 
-        loop {}
-        bail!("Failed to parse instruction: {:x}", value)
+        if value & 0xF0FF == 0xE09E {
+            return Ok(Self::IsKeyPressed {
+                register: ((value >> 8) & 0xF) as u8,
+            });
+        }
+
+        if value & 0xF0FF == 0xE0A1 {
+            return Ok(Self::NotKeyPressed {
+                register: ((value >> 8) & 0xF) as u8,
+            });
+        }
+
+        if value == 0xF000 {
+            return Ok(Self::SetLongIndexRegister);
+        }
+
+        if value & 0xF0FF == 0xF001 {
+            return Ok(Self::SelectPlanes(((value >> 8) & 0xF) as u8));
+        }
+
+        if value == 0xF002 {
+            return Ok(Self::LoadAudioPattern);
+        }
+
+        if value & 0xF000 == 0xF000 {
+            if let Some(instruction) = Self::parse_fxxx(value) {
+                return Ok(instruction);
+            }
+        }
+
+        Err(UnknownOpcode(value))
     }
 }
 
 impl Instruction {
+    /// Whether the instruction exists on the platform emulated by `compatibility_mode`.
+    pub const fn is_supported(self, compatibility_mode: CompatabilityMode) -> bool {
+        match self {
+            Self::ScrollDown(_)
+            | Self::ScrollRight
+            | Self::ScrollLeft
+            | Self::Exit
+            | Self::LowResolution
+            | Self::HighResolution
+            | Self::LargeFontCharacter { .. } => {
+                !matches!(compatibility_mode, CompatabilityMode::Cosmac)
+            }
+            // SUPER-CHIP only had 8 flags.
+            Self::SaveFlags { register } | Self::LoadFlags { register } => match compatibility_mode
+            {
+                CompatabilityMode::Cosmac => false,
+                CompatabilityMode::Super => register < 8,
+                CompatabilityMode::Xo => true,
+            },
+            Self::ScrollUp(_)
+            | Self::StoreRange { .. }
+            | Self::LoadRange { .. }
+            | Self::SetLongIndexRegister
+            | Self::SelectPlanes(_)
+            | Self::LoadAudioPattern
+            | Self::SetPitch { .. } => {
+                matches!(compatibility_mode, CompatabilityMode::Xo)
+            }
+            _ => true,
+        }
+    }
+
     const fn parse_8xxx(value: u16) -> Option<Self> {
         let instruction = ((value) & 0xF) as u8;
         let register_x = ((value >> 8) & 0xF) as u8;
@@ -190,7 +360,7 @@ impl Instruction {
                 register_x,
                 register_y,
             }),
-            6 => Some(Self::ShiftLeft {
+            6 => Some(Self::ShiftRight {
                 register_x,
                 register_y,
             }),
@@ -198,13 +368,35 @@ impl Instruction {
                 register_x,
                 register_y,
             }),
-            0xE => Some(Self::ShiftRight {
+            0xE => Some(Self::ShiftLeft {
                 register_x,
                 register_y,
             }),
             _ => None,
         }
     }
+
+    const fn parse_fxxx(value: u16) -> Option<Self> {
+        let instruction = (value & 0xFF) as u8;
+        let register = ((value >> 8) & 0xF) as u8;
+
+        match instruction {
+            0x07 => Some(Self::GetDelayTimer { register }),
+            0x0A => Some(Self::GetKey { register }),
+            0x15 => Some(Self::SetDelayTimer { register }),
+            0x18 => Some(Self::SetSoundTimer { register }),
+            0x1E => Some(Self::AddToIndex { register }),
+            0x29 => Some(Self::FontCharacter { register }),
+            0x30 => Some(Self::LargeFontCharacter { register }),
+            0x33 => Some(Self::DecimalConversion { register }),
+            0x3A => Some(Self::SetPitch { register }),
+            0x55 => Some(Self::StoreMemory { register }),
+            0x65 => Some(Self::LoadMemory { register }),
+            0x75 => Some(Self::SaveFlags { register }),
+            0x85 => Some(Self::LoadFlags { register }),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -228,6 +420,51 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_parse_scroll_down() {
+        let val: u16 = 0x00C7;
+
+        let instruction: Instruction = val.try_into().unwrap();
+
+        assert_eq!(instruction, Instruction::ScrollDown(7));
+    }
+
+    #[test]
+    fn test_parse_scroll_up() {
+        let val: u16 = 0x00D5;
+
+        let instruction: Instruction = val.try_into().unwrap();
+
+        assert_eq!(instruction, Instruction::ScrollUp(5));
+    }
+
+    #[test]
+    fn test_parse_exit() {
+        let val: u16 = 0x00FD;
+
+        let instruction: Instruction = val.try_into().unwrap();
+
+        assert_eq!(instruction, Instruction::Exit);
+    }
+
+    #[test]
+    fn test_parse_set_long_index_register() {
+        let val: u16 = 0xF000;
+
+        let instruction: Instruction = val.try_into().unwrap();
+
+        assert_eq!(instruction, Instruction::SetLongIndexRegister);
+    }
+
+    #[test]
+    fn test_parse_select_planes() {
+        let val: u16 = 0xF301;
+
+        let instruction: Instruction = val.try_into().unwrap();
+
+        assert_eq!(instruction, Instruction::SelectPlanes(3));
+    }
+
     #[test]
     fn test_parse_goto() {
         let val: u16 = 0x1736;
@@ -237,6 +474,15 @@ mod test {
         assert_eq!(instruction, Instruction::Goto(0x736));
     }
 
+    #[test]
+    fn test_parse_goto_offset() {
+        let val: u16 = 0xB736;
+
+        let instruction: Instruction = val.try_into().unwrap();
+
+        assert_eq!(instruction, Instruction::GotoOffset(0x736));
+    }
+
     #[test]
     fn test_parse_set_register() {
         let val: u16 = 0x6736;
@@ -267,6 +513,21 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_parse_store_range() {
+        let val: u16 = 0x5732;
+
+        let instruction: Instruction = val.try_into().unwrap();
+
+        assert_eq!(
+            instruction,
+            Instruction::StoreRange {
+                register_x: 0x7,
+                register_y: 0x3,
+            }
+        );
+    }
+
     #[test]
     fn test_parse_8xxx() {
         let val = 0x8760;
@@ -281,4 +542,55 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn test_parse_key_pressed() {
+        let val: u16 = 0xE3A1;
+
+        let instruction: Instruction = val.try_into().unwrap();
+
+        assert_eq!(instruction, Instruction::NotKeyPressed { register: 0x3 });
+    }
+
+    #[test]
+    fn test_parse_shifts() {
+        let right: Instruction = 0x8126.try_into().unwrap();
+        let left: Instruction = 0x812E.try_into().unwrap();
+
+        assert_eq!(
+            right,
+            Instruction::ShiftRight {
+                register_x: 0x1,
+                register_y: 0x2,
+            }
+        );
+        assert_eq!(
+            left,
+            Instruction::ShiftLeft {
+                register_x: 0x1,
+                register_y: 0x2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_fxxx() {
+        let val = 0xF733;
+
+        let instruction: Instruction = val.try_into().unwrap();
+
+        assert_eq!(
+            instruction,
+            Instruction::DecimalConversion { register: 0x7 }
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_fxxx() {
+        let val: u16 = 0xF7FF;
+
+        let instruction: Result<Instruction, _> = val.try_into();
+
+        assert_eq!(instruction, Err(UnknownOpcode(0xF7FF)));
+    }
 }
@@ -0,0 +1,56 @@
+use crate::arguments::compatability_mode::CompatabilityMode;
+
+/// Behaviours that differ between CHIP-8 interpreters.
+///
+/// Each quirk is described on its command line override in [`QuirkOverrides`].
+///
+/// [`QuirkOverrides`]: crate::arguments::quirk_overrides::QuirkOverrides
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Quirks {
+    pub shift_uses_vy:           bool,
+    pub memory_increments_index: bool,
+    pub jump_uses_vx:            bool,
+    pub logic_resets_vf:         bool,
+    pub display_wait:            bool,
+    pub wrap_sprites:            bool,
+    pub half_pixel_scroll:       bool,
+    pub count_collided_rows:     bool,
+}
+
+impl From<CompatabilityMode> for Quirks {
+    fn from(compatibility_mode: CompatabilityMode) -> Self {
+        match compatibility_mode {
+            CompatabilityMode::Cosmac => Self {
+                shift_uses_vy:           true,
+                memory_increments_index: true,
+                jump_uses_vx:            false,
+                logic_resets_vf:         true,
+                display_wait:            true,
+                wrap_sprites:            false,
+                half_pixel_scroll:       false,
+                count_collided_rows:     false,
+            },
+            CompatabilityMode::Super => Self {
+                shift_uses_vy:           false,
+                memory_increments_index: false,
+                jump_uses_vx:            true,
+                logic_resets_vf:         false,
+                display_wait:            false,
+                wrap_sprites:            false,
+                half_pixel_scroll:       true,
+                count_collided_rows:     true,
+            },
+            CompatabilityMode::Xo => Self {
+                shift_uses_vy:           true,
+                memory_increments_index: false,
+                jump_uses_vx:            false,
+                logic_resets_vf:         false,
+                display_wait:            false,
+                wrap_sprites:            true,
+                half_pixel_scroll:       false,
+                count_collided_rows:     false,
+            },
+        }
+    }
+}
@@ -0,0 +1,71 @@
+/// Most return addresses any supported platform can hold.
+pub const MAX_DEPTH: usize = 16;
+
+/// Call stack of subroutine return addresses.
+#[derive(Debug, Clone, Copy)]
+pub struct Stack {
+    addresses: [u16; MAX_DEPTH],
+    len:       usize,
+    /// Amount of return addresses that fit before overflowing.
+    depth:     usize,
+}
+
+impl Stack {
+    /// Create an empty stack, `depth` is capped at [`MAX_DEPTH`].
+    pub fn new(depth: usize) -> Self {
+        Self {
+            addresses: [0; MAX_DEPTH],
+            len:       0,
+            depth:     depth.min(MAX_DEPTH),
+        }
+    }
+
+    /// Push a return address, returns `None` if the stack is full.
+    pub const fn push(&mut self, address: u16) -> Option<()> {
+        if self.len == self.depth {
+            return None;
+        }
+
+        self.addresses[self.len] = address;
+        self.len += 1;
+
+        Some(())
+    }
+
+    /// Pop the most recent return address, returns `None` if the stack is empty.
+    pub fn pop(&mut self) -> Option<u16> {
+        self.len = self.len.checked_sub(1)?;
+
+        Some(self.addresses[self.len])
+    }
+
+    /// Return addresses currently on the stack, from the oldest to the most recent call.
+    pub fn addresses(&self) -> &[u16] { &self.addresses[..self.len] }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_push_and_pop() {
+        let mut stack = Stack::new(2);
+
+        assert_eq!(stack.push(0x202), Some(()));
+        assert_eq!(stack.push(0x304), Some(()));
+        assert_eq!(stack.addresses(), [0x202, 0x304]);
+
+        assert_eq!(stack.pop(), Some(0x304));
+        assert_eq!(stack.pop(), Some(0x202));
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut stack = Stack::new(1);
+
+        assert_eq!(stack.push(0x202), Some(()));
+        assert_eq!(stack.push(0x304), None);
+        assert_eq!(stack.addresses(), [0x202]);
+    }
+}
@@ -0,0 +1,31 @@
+pub mod file;
+
+use anyhow::Result;
+
+/// Amount of SUPER-CHIP user flags, XO-CHIP extends them from 8 to 16.
+pub const FLAG_COUNT: usize = 16;
+
+/// Allow the emulator to keep the SUPER-CHIP user flags between runs
+pub trait FlagStorage {
+    /// Load the flags saved by a previous run, or all zeros if there are none.
+    fn load(&mut self) -> Result<[u8; FLAG_COUNT]>;
+
+    /// Save the flags for later runs, called at the end of any frame they changed in.
+    fn save(&mut self, flags: [u8; FLAG_COUNT]) -> Result<()>;
+}
+
+/// Flags that are forgotten once the emulator exits, for when there is nowhere to save them.
+#[derive(Default)]
+pub struct MemoryFlags {
+    flags: [u8; FLAG_COUNT],
+}
+
+impl FlagStorage for MemoryFlags {
+    fn load(&mut self) -> Result<[u8; FLAG_COUNT]> { Ok(self.flags) }
+
+    fn save(&mut self, flags: [u8; FLAG_COUNT]) -> Result<()> {
+        self.flags = flags;
+
+        Ok(())
+    }
+}
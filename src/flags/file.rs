@@ -0,0 +1,129 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+
+use super::{FlagStorage, FLAG_COUNT};
+
+/// Stores the flags of every program in a single file, keyed by a hash of the program.
+///
+/// Each line holds the hash followed by the flags, both in hexadecimal.
+pub struct FlagFile {
+    path: PathBuf,
+    key:  String,
+}
+
+impl FlagFile {
+    pub fn new(path: PathBuf, program: &[u8]) -> Self {
+        Self {
+            path,
+            key: format!("{:016x}", hash(program)),
+        }
+    }
+
+    /// Default location of the flag file in the users data directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|path| path.join("chip8").join("flags"))
+    }
+
+    fn read(&self) -> Result<String> {
+        if !self.path.exists() {
+            return Ok(String::new());
+        }
+
+        fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read flag file: `{}`", self.path.display()))
+    }
+}
+
+impl FlagStorage for FlagFile {
+    fn load(&mut self) -> Result<[u8; FLAG_COUNT]> {
+        let contents = self.read()?;
+
+        let Some(line) = contents
+            .lines()
+            .find(|line| line.split_whitespace().next() == Some(&self.key))
+        else {
+            return Ok([0; FLAG_COUNT]);
+        };
+
+        let Some(encoded) = line.split_whitespace().nth(1) else {
+            bail!("Missing flags for `{}` in flag file", self.key);
+        };
+
+        let mut flags = [0; FLAG_COUNT];
+
+        for (index, flag) in flags.iter_mut().enumerate() {
+            let byte = encoded.get(index * 2..index * 2 + 2);
+
+            let Some(byte) = byte.and_then(|byte| u8::from_str_radix(byte, 16).ok()) else {
+                bail!("Failed to parse flags for `{}` in flag file", self.key);
+            };
+
+            *flag = byte;
+        }
+
+        Ok(flags)
+    }
+
+    fn save(&mut self, flags: [u8; FLAG_COUNT]) -> Result<()> {
+        let contents = self.read()?;
+
+        let encoded = flags.iter().fold(String::new(), |mut encoded, flag| {
+            let _ = write!(encoded, "{flag:02x}");
+            encoded
+        });
+
+        let mut lines = contents
+            .lines()
+            .filter(|line| line.split_whitespace().next() != Some(&self.key))
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+
+        lines.push(format!("{} {encoded}", self.key));
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&self.path, lines.join("\n") + "\n")
+            .with_context(|| format!("Failed to write flag file: `{}`", self.path.display()))
+    }
+}
+
+/// 64 bit FNV-1a, which unlike the standard library hasher is stable between releases.
+fn hash(program: &[u8]) -> u64 {
+    program.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_flags_persist_per_program() {
+        let path = std::env::temp_dir().join(format!("chip8-flags-{}", std::process::id()));
+
+        let mut first = FlagFile::new(path.clone(), &[0x12, 0x00]);
+        let mut second = FlagFile::new(path.clone(), &[0x13, 0x00]);
+
+        let mut flags = [0; FLAG_COUNT];
+        flags[0] = 0xAB;
+        flags[15] = 0x01;
+
+        first.save(flags).unwrap();
+        second.save([0xFF; FLAG_COUNT]).unwrap();
+
+        let loaded = FlagFile::new(path.clone(), &[0x12, 0x00]).load().unwrap();
+        let missing = FlagFile::new(path.clone(), &[0x14, 0x00]).load().unwrap();
+
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded, flags);
+        assert_eq!(missing, [0; FLAG_COUNT]);
+    }
+}
@@ -0,0 +1,9 @@
+use crate::emulator::KEY_COUNT;
+
+/// Allow the emulator to receive key presses from different UIs
+pub trait Input {
+    /// Poll the state of the emulators hexadecimal keypad.
+    ///
+    /// Each item represents whether the key of that index, `0x0` to `0xF`, is held down.
+    fn keys(&mut self) -> [bool; KEY_COUNT];
+}
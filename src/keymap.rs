@@ -0,0 +1,174 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Result};
+use minifb::Key;
+
+use crate::emulator::KEY_COUNT;
+
+/// Host keys for each key on the emulators keypad, from `0x0` to `0xF`.
+///
+/// Laid out on the left side of a QWERTY keyboard:
+///
+/// ```text
+/// 1 2 3 4      1 2 3 C
+/// Q W E R  ->  4 5 6 D
+/// A S D F      7 8 9 E
+/// Z X C V      A 0 B F
+/// ```
+pub const DEFAULT_KEYMAP: [Key; KEY_COUNT] = [
+    Key::X,
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Q,
+    Key::W,
+    Key::E,
+    Key::A,
+    Key::S,
+    Key::D,
+    Key::Z,
+    Key::C,
+    Key::Key4,
+    Key::R,
+    Key::F,
+    Key::V,
+];
+
+/// Parse a keymap for the keypad keys `0x0` to `0xF`, in that order.
+///
+/// Keys are separated by commas or whitespace, such as `x,1,2,3,q,w,e,a,s,d,z,c,4,r,f,v`.
+/// If every key is a single character the separators may be left out, such as
+/// `x123qweasdzc4rfv`.
+pub fn parse_keymap(keymap: &str) -> Result<[Key; KEY_COUNT]> {
+    let keymap = keymap.trim();
+
+    let names = if keymap.contains(|char: char| char == ',' || char.is_whitespace()) {
+        keymap
+            .split(|char: char| char == ',' || char.is_whitespace())
+            .filter(|name| !name.is_empty())
+            .map(str::to_owned)
+            .collect::<Vec<_>>()
+    } else {
+        keymap.chars().map(String::from).collect()
+    };
+
+    if names.len() != KEY_COUNT {
+        bail!(
+            "Keymap must contain {KEY_COUNT} keys, one for each key from 0 to F, found {}",
+            names.len()
+        );
+    }
+
+    let mut keys = DEFAULT_KEYMAP;
+    let mut used = HashSet::new();
+
+    for (index, name) in names.iter().enumerate() {
+        let Some(key) = parse_key(name) else {
+            bail!("Failed to parse key `{name}` for keypad key {index:X}")
+        };
+
+        if !used.insert(key) {
+            bail!("Key `{name}` is mapped to more than one keypad key");
+        }
+
+        keys[index] = key;
+    }
+
+    Ok(keys)
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    Some(match name.to_lowercase().as_str() {
+        "0" => Key::Key0,
+        "1" => Key::Key1,
+        "2" => Key::Key2,
+        "3" => Key::Key3,
+        "4" => Key::Key4,
+        "5" => Key::Key5,
+        "6" => Key::Key6,
+        "7" => Key::Key7,
+        "8" => Key::Key8,
+        "9" => Key::Key9,
+        "a" => Key::A,
+        "b" => Key::B,
+        "c" => Key::C,
+        "d" => Key::D,
+        "e" => Key::E,
+        "f" => Key::F,
+        "g" => Key::G,
+        "h" => Key::H,
+        "i" => Key::I,
+        "j" => Key::J,
+        "k" => Key::K,
+        "l" => Key::L,
+        "m" => Key::M,
+        "n" => Key::N,
+        "o" => Key::O,
+        "p" => Key::P,
+        "q" => Key::Q,
+        "r" => Key::R,
+        "s" => Key::S,
+        "t" => Key::T,
+        "u" => Key::U,
+        "v" => Key::V,
+        "w" => Key::W,
+        "x" => Key::X,
+        "y" => Key::Y,
+        "z" => Key::Z,
+        "num0" => Key::NumPad0,
+        "num1" => Key::NumPad1,
+        "num2" => Key::NumPad2,
+        "num3" => Key::NumPad3,
+        "num4" => Key::NumPad4,
+        "num5" => Key::NumPad5,
+        "num6" => Key::NumPad6,
+        "num7" => Key::NumPad7,
+        "num8" => Key::NumPad8,
+        "num9" => Key::NumPad9,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "space" => Key::Space,
+        "`" | "backquote" => Key::Backquote,
+        "-" | "minus" => Key::Minus,
+        "=" | "equal" => Key::Equal,
+        "[" | "leftbracket" => Key::LeftBracket,
+        "]" | "rightbracket" => Key::RightBracket,
+        ";" | "semicolon" => Key::Semicolon,
+        "'" | "apostrophe" => Key::Apostrophe,
+        "\\" | "backslash" => Key::Backslash,
+        "." | "period" => Key::Period,
+        "/" | "slash" => Key::Slash,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_compact_keymap() {
+        let keymap = parse_keymap("x123qweasdzc4rfv").unwrap();
+
+        assert_eq!(keymap, DEFAULT_KEYMAP);
+    }
+
+    #[test]
+    fn test_parse_separated_keymap() {
+        let keymap = parse_keymap("x, 1, 2, 3, a, z, e, q, s, d, w, c, 4, r, f, v").unwrap();
+
+        assert_eq!(keymap[0x4], Key::A);
+        assert_eq!(keymap[0x5], Key::Z);
+        assert_eq!(keymap[0x7], Key::Q);
+    }
+
+    #[test]
+    fn test_parse_invalid_keymap() {
+        assert!(parse_keymap("x123").is_err());
+        assert!(parse_keymap("x123qweasdzc4rfx").is_err());
+        assert!(parse_keymap("x123qweasdzc4rf!").is_err());
+    }
+}
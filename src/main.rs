@@ -2,38 +2,93 @@ mod arguments;
 mod color;
 mod draw;
 mod emulator;
+mod flags;
+mod input;
+mod keymap;
+mod sound;
 mod ui;
 
 use std::fs;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use arguments::scale::Scale;
 use arguments::Arguments;
 use clap::Parser;
-use color::parse_color;
-use emulator::Chip8;
+use color::{parse_color, parse_palette, BOTH_PLANES_COLOR, SECOND_PLANE_COLOR};
+use emulator::{Chip8, Quirks};
+use flags::file::FlagFile;
+use flags::{FlagStorage, MemoryFlags};
+use keymap::{parse_keymap, DEFAULT_KEYMAP};
+#[cfg(feature = "audio")]
+use sound::beeper::Beeper;
+use sound::{Silent, Sound};
 use ui::Ui;
 
-const WIDTH: usize = 64;
-const HEIGHT: usize = 32;
-
 fn main() -> Result<()> {
     let args = Arguments::parse();
 
     let program = fs::read(args.binary_path)?;
 
-    let fg = parse_color(&args.foreground_color)?;
+    let mut flags: Box<dyn FlagStorage> =
+        if let Some(path) = args.flags_file.or_else(FlagFile::default_path) {
+            Box::new(FlagFile::new(path, &program))
+        } else {
+            eprintln!(
+                "Failed to find a data directory, user flags will not be kept between runs. \
+                 Pass a file for them with `--flags-file`."
+            );
+
+            Box::new(MemoryFlags::default())
+        };
 
-    let bg = parse_color(&args.background_color)?;
+    let palette = if let Some(palette) = &args.palette {
+        parse_palette(palette)?
+    } else {
+        [
+            parse_color(&args.background_color)?,
+            parse_color(&args.foreground_color)?,
+            SECOND_PLANE_COLOR,
+            BOTH_PLANES_COLOR,
+        ]
+    };
 
     let scale = args.scale.unwrap_or(Scale::X8);
 
-    let mut chip = Chip8::new(&program, args.compatability_mode);
+    let keymap = match (args.keymap, args.keymap_file) {
+        (Some(keymap), _) => parse_keymap(&keymap)?,
+        (_, Some(path)) => {
+            let keymap = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read keymap file: `{}`", path.display()))?;
+
+            parse_keymap(&keymap)?
+        }
+        (None, None) => DEFAULT_KEYMAP,
+    };
+
+    #[cfg(feature = "audio")]
+    let mut sound: Box<dyn Sound> = if args.mute {
+        Box::new(Silent)
+    } else {
+        Box::new(Beeper::new()?)
+    };
+
+    #[cfg(not(feature = "audio"))]
+    let mut sound: Box<dyn Sound> = Box::new(Silent);
+
+    let quirks = args.quirks.apply(Quirks::from(args.compatability_mode));
+
+    let mut chip = Chip8::new(&program, args.compatability_mode, quirks);
 
     // TODO: figure out how to fix the ui not fully rendering on call.
-    let mut ui = Ui::new(fg, bg, scale);
+    let mut ui = Ui::new(palette, scale, keymap);
+
+    if let Err(error) = chip.run(&mut ui, sound.as_mut(), flags.as_mut()) {
+        if error.is::<emulator::Error>() {
+            eprintln!("Call stack: {:#05X?}", chip.call_stack());
+        }
 
-    chip.run(&mut ui)?;
+        return Err(error);
+    }
 
     Ok(())
 }
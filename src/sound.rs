@@ -0,0 +1,46 @@
+#[cfg(feature = "audio")]
+pub mod beeper;
+#[cfg(any(feature = "audio", test))]
+pub mod waveform;
+
+use anyhow::Result;
+
+/// Size in bytes of an XO-CHIP audio pattern.
+pub const PATTERN_SIZE: usize = 16;
+
+/// Pattern played until a program loads its own, a square wave.
+pub const DEFAULT_PATTERN: [u8; PATTERN_SIZE] = [
+    0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00,
+];
+
+/// Pitch played until a program sets its own, plays back 4000 bits per second.
+pub const DEFAULT_PITCH: u8 = 64;
+
+/// Allow the emulator to be switched out with different audio outputs
+pub trait Sound {
+    /// Start or stop the emulators tone.
+    ///
+    /// Called once every frame, `true` while the sound timer is active.
+    fn set_tone(&mut self, active: bool) -> Result<()>;
+
+    /// Replace the XO-CHIP audio pattern played while the tone is active.
+    ///
+    /// Each bit of the pattern is a single sample, from the most significant bit of the
+    /// first byte to the least significant bit of the last.
+    fn set_pattern(&mut self, pattern: [u8; PATTERN_SIZE]) -> Result<()>;
+
+    /// Set the XO-CHIP pitch register, the pattern plays back at
+    /// `4000 * 2 ^ ((pitch - 64) / 48)` bits per second.
+    fn set_pitch(&mut self, pitch: u8) -> Result<()>;
+}
+
+/// Audio output that plays nothing, for headless runs or when muted.
+pub struct Silent;
+
+impl Sound for Silent {
+    fn set_tone(&mut self, _active: bool) -> Result<()> { Ok(()) }
+
+    fn set_pattern(&mut self, _pattern: [u8; PATTERN_SIZE]) -> Result<()> { Ok(()) }
+
+    fn set_pitch(&mut self, _pitch: u8) -> Result<()> { Ok(()) }
+}
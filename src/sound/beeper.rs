@@ -0,0 +1,100 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{SampleFormat, Stream};
+
+use super::waveform::Waveform;
+use super::{Sound, PATTERN_SIZE};
+
+const VOLUME: f32 = 0.1;
+
+/// Plays the emulators waveform on the default output device while the tone is active.
+pub struct Beeper {
+    active:   Arc<AtomicBool>,
+    waveform: Arc<Mutex<Waveform>>,
+    // Audio stops playing once the stream is dropped.
+    _stream:  Stream,
+}
+
+impl Beeper {
+    pub fn new() -> Result<Self> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .context("No audio output device available")?;
+
+        let config = device
+            .supported_output_configs()?
+            .find(|config| config.sample_format() == SampleFormat::F32)
+            .context("Audio output device does not support floating point samples")?
+            .with_max_sample_rate()
+            .config();
+
+        #[allow(clippy::cast_precision_loss)]
+        let sample_rate = config.sample_rate.0 as f32;
+        let channels = usize::from(config.channels);
+
+        let active = Arc::new(AtomicBool::new(false));
+        let waveform = Arc::new(Mutex::new(Waveform::default()));
+
+        let stream_active = Arc::clone(&active);
+        let stream_waveform = Arc::clone(&waveform);
+
+        let stream = device.build_output_stream(
+            &config,
+            move |data: &mut [f32], _| {
+                let active = stream_active.load(Ordering::Relaxed);
+                let mut waveform = stream_waveform
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+
+                for frame in data.chunks_mut(channels) {
+                    let sample = if !active {
+                        0.0
+                    } else if waveform.next_sample(sample_rate) {
+                        VOLUME
+                    } else {
+                        -VOLUME
+                    };
+
+                    frame.fill(sample);
+                }
+            },
+            |error| eprintln!("Audio stream error: {error}"),
+            None,
+        )?;
+
+        stream.play()?;
+
+        Ok(Self {
+            active,
+            waveform,
+            _stream: stream,
+        })
+    }
+
+    fn waveform(&self) -> MutexGuard<'_, Waveform> {
+        self.waveform.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Sound for Beeper {
+    fn set_tone(&mut self, active: bool) -> Result<()> {
+        self.active.store(active, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    fn set_pattern(&mut self, pattern: [u8; PATTERN_SIZE]) -> Result<()> {
+        self.waveform().set_pattern(pattern);
+
+        Ok(())
+    }
+
+    fn set_pitch(&mut self, pitch: u8) -> Result<()> {
+        self.waveform().set_pitch(pitch);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use super::{DEFAULT_PATTERN, DEFAULT_PITCH, PATTERN_SIZE};
+
+/// Amount of bits, and so samples, in a pattern.
+const PATTERN_BITS: f32 = 128.0;
+
+/// Plays back an XO-CHIP audio pattern, one bit per sample, at the rate set by its pitch.
+#[derive(Debug, Clone, Copy)]
+pub struct Waveform {
+    pattern:       [u8; PATTERN_SIZE],
+    /// Bits played every second.
+    playback_rate: f32,
+    /// Index of the bit currently played, including how far through it playback is.
+    position:      f32,
+}
+
+impl Default for Waveform {
+    fn default() -> Self {
+        let mut waveform = Self {
+            pattern:       DEFAULT_PATTERN,
+            playback_rate: 0.0,
+            position:      0.0,
+        };
+
+        waveform.set_pitch(DEFAULT_PITCH);
+
+        waveform
+    }
+}
+
+impl Waveform {
+    pub const fn set_pattern(&mut self, pattern: [u8; PATTERN_SIZE]) { self.pattern = pattern; }
+
+    pub fn set_pitch(&mut self, pitch: u8) {
+        self.playback_rate = 4000.0 * ((f32::from(pitch) - 64.0) / 48.0).exp2();
+    }
+
+    /// Get the next sample for an output playing `sample_rate` samples per second.
+    ///
+    /// `true` is high, `false` is low.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn next_sample(&mut self, sample_rate: f32) -> bool {
+        let bit = self.position as usize;
+        let sample = (self.pattern[bit / 8] >> (7 - bit % 8)) & 1 == 1;
+
+        self.position = (self.position + self.playback_rate / sample_rate) % PATTERN_BITS;
+
+        sample
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_default_pitch_plays_one_bit_per_sample() {
+        let mut waveform = Waveform::default();
+
+        let samples = (0..16)
+            .map(|_| waveform.next_sample(4000.0))
+            .collect::<Vec<_>>();
+
+        assert_eq!(samples[..8], [true; 8]);
+        assert_eq!(samples[8..], [false; 8]);
+    }
+
+    #[test]
+    fn test_pitch_doubles_every_48_steps() {
+        let mut waveform = Waveform::default();
+        waveform.set_pattern([0b1010_1010; PATTERN_SIZE]);
+        waveform.set_pitch(DEFAULT_PITCH + 48);
+
+        let samples = (0..8)
+            .map(|_| waveform.next_sample(4000.0))
+            .collect::<Vec<_>>();
+
+        assert_eq!(samples, [true; 8]);
+    }
+}
@@ -1,60 +1,63 @@
 use anyhow::{bail, Result};
 use hex_color::HexColor;
-use minifb::{Key, Window, WindowOptions};
+use minifb::{Key, ScaleMode, Window, WindowOptions};
 
 use crate::arguments::scale::Scale;
 use crate::draw::Draw;
-use crate::{HEIGHT, WIDTH};
+use crate::emulator::{COLOR_COUNT, HIRES_HEIGHT, HIRES_WIDTH, KEY_COUNT};
+use crate::input::Input;
 
 pub struct Ui {
-    window:           Window,
-    foreground_color: HexColor,
-    background_color: HexColor,
+    window:  Window,
+    /// Colour of each combination of lit planes.
+    palette: [HexColor; COLOR_COUNT],
+    keymap:  [Key; KEY_COUNT],
 }
 
 impl Ui {
-    pub fn new(foreground_color: HexColor, background_color: HexColor, scale: Scale) -> Self {
+    pub fn new(palette: [HexColor; COLOR_COUNT], scale: Scale, keymap: [Key; KEY_COUNT]) -> Self {
+        // The window fits a high resolution buffer, so `scale` is halved to keep low resolution
+        // programs at the requested size.
         let options = WindowOptions {
-            scale: scale.into(),
+            scale: scale.halved().into(),
+            // Low resolution buffers are stretched to fill the window, doubling each pixel.
+            scale_mode: ScaleMode::Stretch,
             ..WindowOptions::default()
         };
 
-        let mut window =
-            Window::new("Chip8 - ESC to exit", WIDTH, HEIGHT, options).unwrap_or_else(|e| {
+        let mut window = Window::new("Chip8 - ESC to exit", HIRES_WIDTH, HIRES_HEIGHT, options)
+            .unwrap_or_else(|e| {
                 panic!("{}", e);
             });
 
-        // Limit to max ~60 fps update rate
-        window.limit_update_rate(Some(std::time::Duration::from_micros(16600)));
+        // The emulator paces its own frames.
+        window.limit_update_rate(None);
 
         Self {
             window,
-            foreground_color,
-            background_color,
+            palette,
+            keymap,
         }
     }
 }
 
 impl Draw for Ui {
-    fn draw(&mut self, buffer: &[[bool; 64]; 32]) -> Result<()> {
+    fn draw(&mut self, buffer: &[u8], width: usize, height: usize) -> Result<()> {
         if !self.window.is_open() || self.window.is_key_down(Key::Escape) {
             bail!("Window closed");
         }
 
         let buffer = buffer
             .iter()
-            .flatten()
-            .map(|val| {
-                if *val {
-                    self.foreground_color.to_u24()
-                } else {
-                    self.background_color.to_u24()
-                }
-            })
+            .map(|planes| self.palette[usize::from(*planes) % COLOR_COUNT].to_u24())
             .collect::<Vec<u32>>();
 
-        self.window.update_with_buffer(&buffer, WIDTH, HEIGHT)?;
+        self.window.update_with_buffer(&buffer, width, height)?;
 
         Ok(())
     }
 }
+
+impl Input for Ui {
+    fn keys(&mut self) -> [bool; KEY_COUNT] { self.keymap.map(|key| self.window.is_key_down(key)) }
+}